@@ -1,102 +1,3024 @@
-use std::{collections::HashMap, fmt::Display, io::Write, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    io::Write,
+};
 
-use crate::*;
+use heck::{ToPascalCase, ToSnakeCase, ToTitleCase};
 
-pub fn emit_der(f: &mut dyn Write, Doc { links, defs }: &Doc) -> std::io::Result<()> {
+use crate::{
+    json::{self, Json},
+    types::Scalar,
+    *,
+};
+
+/// Knobs shared by all emitters.
+#[derive(Debug, Clone, Default)]
+pub struct EmitOptions {
+    /// Treat each entity's first field as its primary key.
+    pub first_field_pk: bool,
+    /// How primary and foreign key columns are named.
+    pub naming: Naming,
+    /// Keep relationship nodes on a rank of their own between the entities
+    /// they connect.
+    pub label_rank: bool,
+    /// Color edges by whether their participation is optional.
+    pub color_participation: bool,
+    /// Draw key fields in their own compartment above the others.
+    pub key_compartment: bool,
+    /// Only draw key fields, plus the fk columns of ORM diagrams.
+    pub keys_only: bool,
+    /// Draw entities as plain named boxes without their fields.
+    pub skeleton: bool,
+    /// Version of the JSON format to emit, the latest if unset.
+    pub json_version: Option<u32>,
+    /// How entity names are displayed, as written if unset.
+    pub name_style: Option<NameStyle>,
+    /// Indentation of generated code, the target language's usual one if
+    /// unset.
+    pub indent: Option<Indent>,
+    /// How cardinalities are written on diagrams.
+    pub notation: Notation,
+    /// Per-count overrides of `notation`.
+    pub symbols: Vec<(LinkN, String)>,
+    /// Graphviz layout engine of diagrams, `dot` if unset.
+    pub layout: Option<String>,
+    /// Direction of diagrams, Graphviz's default (top to bottom) if unset.
+    pub direction: Option<Direction>,
+    /// Add a legend explaining the notation to diagrams.
+    pub legend: bool,
+    /// Colors and font of diagrams.
+    pub theme: Theme,
+    /// Rows of sample data SQL output inserts into each table.
+    pub fixtures: Option<usize>,
+}
+
+impl EmitOptions {
+    /// Entity name as it should be displayed or generated.
+    pub fn entity_name(&self, name: &str) -> String {
+        match self.name_style {
+            None => name.to_owned(),
+            Some(NameStyle::Pascal) => name.to_pascal_case(),
+            Some(NameStyle::Snake) => name.to_snake_case(),
+            Some(NameStyle::Title) => name.to_title_case(),
+        }
+    }
+
+    /// Entity name as a type in generated code. Names that aren't
+    /// identifiers, like quoted ones with spaces, are made Pascal case.
+    pub fn type_name(&self, name: &str) -> String {
+        let name = self.entity_name(name);
+        if is_identifier(&name) {
+            name
+        } else {
+            name.to_pascal_case()
+        }
+    }
+
+    /// One level of indentation for generated code, `spaces` wide unless
+    /// `--indent` says otherwise.
+    fn indent(&self, spaces: usize) -> String {
+        match self.indent {
+            None => " ".repeat(spaces),
+            Some(Indent::Spaces(n)) => " ".repeat(n),
+            Some(Indent::Tab) => "\t".to_owned(),
+        }
+    }
+
+    /// Extra edge attributes for the side of a link with `count`.
+    fn participation(&self, count: LinkN) -> String {
+        match (self.color_participation, count.is_optional()) {
+            (false, _) => String::new(),
+            (true, false) => format!(", color=\"{}\"", self.theme.line_color.unwrap_or("black")),
+            (true, true) => ", color=gray".to_owned(),
+        }
+    }
+
+    /// Text shown for a cardinality.
+    pub fn cardinality(&self, count: LinkN) -> String {
+        match self.symbols.iter().find(|(n, _)| *n == count) {
+            Some((_, symbol)) => symbol.clone(),
+            None => self.notation.symbol(count).to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl std::str::FromStr for Indent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tab" | "tabs" => Ok(Indent::Tab),
+            _ => s
+                .parse()
+                .map(Indent::Spaces)
+                .map_err(|_| format!("expected a number of spaces or `tab`, got `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum NameStyle {
+    /// `HttpServer`
+    Pascal,
+    /// `http_server`
+    Snake,
+    /// `Http Server`
+    Title,
+}
+
+/// Colors and font of diagrams. Unset ones are left to Graphviz.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// Color of the canvas.
+    pub background: Option<&'static str>,
+    /// Fill of DER entity titles.
+    pub header: &'static str,
+    /// Fill behind ORM edge labels, so crossing edges don't run through them.
+    pub label: &'static str,
+    /// Color of all text.
+    pub font_color: Option<&'static str>,
+    /// Color of edges and borders.
+    pub line_color: Option<&'static str>,
+    /// Font of all text.
+    pub font_name: Option<String>,
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Theme {
+            background: None,
+            header: "gray",
+            label: "white",
+            font_color: None,
+            line_color: None,
+            font_name: None,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Theme {
+            background: Some("#1e1e1e"),
+            header: "#3c3c3c",
+            label: "#1e1e1e",
+            font_color: Some("#e0e0e0"),
+            line_color: Some("#a0a0a0"),
+            font_name: None,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+/// Built-in themes, picked with `--theme`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ThemeName {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl From<ThemeName> for Theme {
+    fn from(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Light => Theme::light(),
+            ThemeName::Dark => Theme::dark(),
+        }
+    }
+}
+
+/// Direction ranks are laid out in, Graphviz's `rankdir`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::EnumString, strum::IntoStaticStr)]
+#[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
+pub enum Direction {
+    /// Top to bottom
+    TB,
+    /// Left to right
+    LR,
+    /// Bottom to top
+    BT,
+    /// Right to left
+    RL,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, strum::EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Notation {
+    /// `1`, `(0,1)`, `(1,N)` and `(0,N)`.
+    #[default]
+    MinMax,
+    /// `1` and `N`, without optionality.
+    Chen,
+    /// UML multiplicities like `0..*`.
+    Uml,
+    /// Crow's foot glyphs spelled out as text, like `}o`.
+    CrowsfootText,
+}
+
+impl Notation {
+    pub fn symbol(self, count: LinkN) -> &'static str {
+        use LinkN::*;
+        match (self, count) {
+            (Notation::MinMax, One) => "1",
+            (Notation::MinMax, MaybeOne) => "(0,1)",
+            (Notation::MinMax, Many) => "(1,N)",
+            (Notation::MinMax, MaybeMany) => "(0,N)",
+            (Notation::Chen, One | MaybeOne) => "1",
+            (Notation::Chen, Many | MaybeMany) => "N",
+            (Notation::Uml, One) => "1",
+            (Notation::Uml, MaybeOne) => "0..1",
+            (Notation::Uml, Many) => "1..*",
+            (Notation::Uml, MaybeMany) => "0..*",
+            (Notation::CrowsfootText, One) => "||",
+            (Notation::CrowsfootText, MaybeOne) => "|o",
+            (Notation::CrowsfootText, Many) => "}|",
+            (Notation::CrowsfootText, MaybeMany) => "}o",
+        }
+    }
+}
+
+/// Latest version of the format written by `emit_json`. Bumped on every
+/// breaking change to it.
+pub const JSON_VERSION: u32 = 1;
+
+/// Signature shared by the emitters of every mode.
+pub type EmitFn = fn(&mut dyn Write, &Doc, &EmitOptions) -> std::io::Result<()>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+/// Output modes, picked with `-m`.
+pub enum Mode {
+    #[default]
+    Der,
+    Orm,
+    Json,
+    #[strum(serialize = "es-mapping")]
+    EsMapping,
+    Prose,
+    Pydantic,
+    Mongoose,
+    GraphMl,
+    Mermaid,
+    Outline,
+    Sql,
+    Dbml,
+    Ts,
+    Prisma,
+    Graphql,
+}
+
+impl Mode {
+    /// The emitter writing this mode's output.
+    pub fn emitter(&self) -> EmitFn {
+        match self {
+            Mode::Der => emit_der,
+            Mode::Orm => emit_orm,
+            Mode::Json => emit_json,
+            Mode::EsMapping => emit_es_mapping,
+            Mode::Prose => emit_prose,
+            Mode::Pydantic => emit_pydantic,
+            Mode::Mongoose => emit_mongoose,
+            Mode::GraphMl => emit_graphml,
+            Mode::Mermaid => emit_mermaid,
+            Mode::Outline => emit_outline,
+            Mode::Sql => emit_sql,
+            Mode::Dbml => emit_dbml,
+            Mode::Ts => emit_ts,
+            Mode::Prisma => emit_prisma,
+            Mode::Graphql => emit_graphql,
+        }
+    }
+
+    /// Whether this mode gives entities a foreign key field for their links.
+    pub fn has_fk_columns(&self) -> bool {
+        matches!(
+            self,
+            Mode::Orm
+                | Mode::EsMapping
+                | Mode::Pydantic
+                | Mode::Mongoose
+                | Mode::Sql
+                | Mode::Dbml
+                | Mode::Ts
+                | Mode::Prisma
+        )
+    }
+
+    /// Whether this mode emits Graphviz DOT, which can be rendered to images
+    /// by piping it through `dot`.
+    pub fn is_graphviz(&self) -> bool {
+        matches!(self, Mode::Der | Mode::Orm)
+    }
+
+    /// Whether this mode emits tables, where many-to-many links need a
+    /// junction table.
+    pub fn is_relational(&self) -> bool {
+        matches!(self, Mode::Orm | Mode::Sql | Mode::Dbml)
+    }
+
+    /// Line comment marker of the language this mode emits, if it has one.
+    pub fn line_comment(&self) -> Option<&'static str> {
+        match self {
+            Mode::Der | Mode::Orm | Mode::Mongoose | Mode::Dbml | Mode::Ts | Mode::Prisma => {
+                Some("//")
+            }
+            Mode::Mermaid => Some("%%"),
+            Mode::Sql => Some("--"),
+            Mode::Pydantic | Mode::Graphql => Some("#"),
+            Mode::Json | Mode::EsMapping | Mode::Prose | Mode::GraphMl | Mode::Outline => None,
+        }
+    }
+
+    /// File extension for this mode's output, when not rendered with `dot`.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Mode::Der | Mode::Orm => "dot",
+            Mode::Json | Mode::EsMapping => "json",
+            Mode::Prose | Mode::Outline => "txt",
+            Mode::Pydantic => "py",
+            Mode::Mongoose => "js",
+            Mode::GraphMl => "graphml",
+            Mode::Mermaid => "mmd",
+            Mode::Sql => "sql",
+            Mode::Dbml => "dbml",
+            Mode::Ts => "ts",
+            Mode::Prisma => "prisma",
+            Mode::Graphql => "graphql",
+        }
+    }
+}
+
+/// Emits `doc` in `mode` to a string, handy to compare against expected
+/// output.
+pub fn emit_to_string(mode: Mode, doc: &Doc, opts: &EmitOptions) -> std::io::Result<String> {
+    let mut out = vec![];
+    mode.emitter()(&mut out, doc, opts)?;
+    String::from_utf8(out).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+/// Source longer than this many lines is truncated by `emit_source_comment`.
+const EMBED_SOURCE_MAX_LINES: usize = 200;
+
+/// Writes `source` as a block of `comment`-prefixed lines.
+pub fn emit_source_comment(f: &mut dyn Write, source: &str, comment: &str) -> std::io::Result<()> {
+    let lines: Vec<&str> = source.lines().collect();
+    writeln!(f, "{comment} Generated by dialang from:")?;
+    for line in lines.iter().take(EMBED_SOURCE_MAX_LINES) {
+        writeln!(f, "{comment} {line}")?;
+    }
+    if lines.len() > EMBED_SOURCE_MAX_LINES {
+        let rest = lines.len() - EMBED_SOURCE_MAX_LINES;
+        writeln!(f, "{comment} ... ({rest} more lines truncated)")?;
+    }
+    writeln!(f)?;
+    Ok(())
+}
+
+/// Writes a `comment`-prefixed line counting the entities and links of `doc`.
+pub fn emit_summary_comment(f: &mut dyn Write, doc: &Doc, comment: &str) -> std::io::Result<()> {
+    let count =
+        |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+    let entities = doc.defs.iter().filter(|def| !def.stub).count();
+    writeln!(
+        f,
+        "{comment} {}, {}",
+        count(entities, "entity", "entities"),
+        count(doc.links.len(), "relationship", "relationships")
+    )
+}
+
+/// Writes a report on the size of `doc`: how many entities, fields and links
+/// of each cardinality it has, and which entities take part in the most links.
+pub fn emit_stats(f: &mut dyn Write, doc: &Doc) -> std::io::Result<()> {
+    let defs: Vec<&Def> = doc.defs.iter().filter(|def| !def.stub).collect();
+    let fields: usize = defs.iter().map(|def| def.fields.len()).sum();
+    writeln!(f, "Entities: {}", defs.len())?;
+    writeln!(f, "Fields: {fields}")?;
+    writeln!(f, "Relationships: {}", doc.links.len())?;
+    let mut cardinalities: BTreeMap<String, usize> = BTreeMap::new();
+    for link in &doc.links {
+        let arrow = format!("{}--{}", link.from_count.source(), link.to_count.source());
+        *cardinalities.entry(arrow).or_default() += 1;
+    }
+    for (arrow, n) in cardinalities {
+        writeln!(f, "  {arrow}: {n}")?;
+    }
+    let mut degrees: Vec<(&str, usize)> = def_links(&doc.defs, &doc.links)
+        .into_iter()
+        .map(|(name, from)| {
+            let to = doc.links.iter().filter(|link| link.to == name).count();
+            (name, from.len() + to)
+        })
+        .filter(|&(_, degree)| degree > 0)
+        .collect();
+    degrees.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !degrees.is_empty() {
+        let hubs: Vec<String> = degrees
+            .iter()
+            .take(5)
+            .map(|(name, degree)| format!("{name} ({degree})"))
+            .collect();
+        writeln!(f, "Most linked: {}", hubs.join(", "))?;
+    }
+    Ok(())
+}
+
+pub fn emit_der(
+    f: &mut dyn Write,
+    Doc {
+        links,
+        defs,
+        groups,
+        inherits,
+        ..
+    }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
     writeln!(f, "graph {{")?;
+    if let Some(layout) = &opts.layout {
+        writeln!(f, "graph [layout={layout}];")?;
+    }
+    emit_direction(f, opts)?;
+    emit_theme(f, &opts.theme)?;
     writeln!(f, "node [shape=plaintext];")?;
-    for def in defs {
-        let name = &def.name;
-        writeln!(
-            f,
-            r#"{name} [label=<
+    for (i, (group, defs)) in clusters(defs, groups).into_iter().enumerate() {
+        open_cluster(f, i, group)?;
+        for def in defs {
+            let name = &def.name;
+            let title = struck(&escape_html(&opts.entity_name(name)), &def.deprecated);
+            emit_deprecation(f, name, &def.deprecated)?;
+            if def.stub {
+                emit_stub(f, name, &title)?;
+                continue;
+            }
+            let id = node_id(name);
+            if opts.skeleton {
+                let shape = def.shape.as_ref().map_or("box".to_owned(), |shape| {
+                    format!("\"{}\"", dot_escape(shape))
+                });
+                writeln!(f, "{id} [label=<{title}>, shape={shape}];")?;
+                continue;
+            }
+            writeln!(
+                f,
+                r#"{id} [label=<
             <TABLE border="0" cellborder="1" cellspacing="0">
-            <TR><TD colspan="2" bgcolor="gray">{name}</TD></TR>"#
-        )?;
-        for Field { field_type, name } in &def.fields {
-            writeln!(f, "<TR><TD>{field_type}</TD><TD>{name}</TD></TR>")?;
+            <TR><TD colspan="2" bgcolor="{}">{title}</TD></TR>"#,
+                opts.theme.header
+            )?;
+            let rows = def
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| (def.is_pk(i, opts.first_field_pk, &opts.naming), field))
+                .filter(|(is_pk, _)| *is_pk || !opts.keys_only);
+            for (i, compartment) in compartments(rows, opts).into_iter().enumerate() {
+                if i > 0 {
+                    writeln!(f, "<HR/>")?;
+                }
+                for field in compartment {
+                    let attrs = cell_tooltip(field);
+                    let field_type = struck(&escape_html(&field.type_name()), &field.deprecated);
+                    let name = struck(&escape_html(&field.name), &field.deprecated);
+                    writeln!(
+                        f,
+                        "<TR><TD{attrs}>{field_type}</TD><TD{attrs}>{name}</TD></TR>"
+                    )?;
+                }
+            }
+            emit_checks(f, &def.checks)?;
+            writeln!(f, "</TABLE> >{}];", shape_attr(def))?;
         }
-        writeln!(f, "</TABLE> >];")?;
+        close_cluster(f, group)?;
     }
     writeln!(f, "node [shape=diamond, fontsize=11];")?;
-    for Link {
-        from,
-        from_count,
-        to_count,
-        to,
-        label,
-        ..
-    } in links
-    {
+    let mut ids = HashSet::new();
+    for link in links {
+        let Link {
+            from,
+            from_count,
+            to_count,
+            to,
+            label,
+            ..
+        } = link;
+        let tooltip = tooltip(link);
         let label = label.as_deref().unwrap_or_default();
-        let id = format!("{from}_{to}_{label}");
-        writeln!(f, "\t{id} [label=<{label}>];")?;
-        writeln!(f, "\t{from} -- {id} [taillabel=<{from_count}>];")?;
-        writeln!(f, "\t{id} -- {to}   [headlabel=<{to_count}>];")?;
+        // Links between the same pair with the same label would share a
+        // diamond, so later ones get a numeric suffix.
+        let mut id = format!("{from}_{to}_{label}");
+        for n in 2.. {
+            if ids.insert(id.clone()) {
+                break;
+            }
+            id = format!("{from}_{to}_{label}_{n}");
+        }
+        let (from_color, to_color) = (
+            opts.participation(*from_count),
+            opts.participation(*to_count),
+        );
+        let (from_count, to_count) = (opts.cardinality(*from_count), opts.cardinality(*to_count));
+        let weight = weight(link);
+        let line = line_style(link);
+        let (id, from, to) = (node_id(&id), node_id(from), node_id(to));
+        if link.fields.is_empty() || opts.skeleton || opts.keys_only {
+            writeln!(f, "\t{id} [label=<{}>];", escape_html(label))?;
+        } else {
+            // The relationship's own attributes go under its name.
+            writeln!(
+                f,
+                r#"	{id} [label=<<TABLE border="0" cellborder="0" cellspacing="0"><TR><TD>{}</TD></TR>"#,
+                escape_html(label)
+            )?;
+            for field in &link.fields {
+                let field_type = struck(&escape_html(&field.type_name()), &field.deprecated);
+                let name = struck(&escape_html(&field.name), &field.deprecated);
+                writeln!(f, "<TR><TD>{field_type} {name}</TD></TR>")?;
+            }
+            writeln!(f, "</TABLE>>];")?;
+        }
+        writeln!(
+            f,
+            "\t{from} -- {id} [taillabel=<{from_count}>, {tooltip}{from_color}{weight}{line}];"
+        )?;
+        writeln!(
+            f,
+            "\t{id} -- {to}   [headlabel=<{to_count}>, {tooltip}{to_color}{weight}{line}];"
+        )?;
+        if opts.label_rank && from != to {
+            // An invisible edge spanning two ranks leaves room for the
+            // relationship node in between.
+            writeln!(f, "\t{from} -- {to} [style=invis, minlen=2];")?;
+        }
+    }
+    for inherit in inherits {
+        let (child, parent) = (node_id(&inherit.child), node_id(&inherit.parent));
+        writeln!(f, "\t{child} -- {parent} [dir=forward, arrowhead=empty];")?;
+    }
+    if opts.legend {
+        let mut rows = vec![(
+            "&#9671;".to_owned(),
+            "relationship, named after the link's label",
+        )];
+        rows.extend(LinkN::ALL.map(|count| (opts.cardinality(count), count.describe())));
+        rows.extend(inheritance_legend(inherits));
+        emit_legend(f, &rows)?;
     }
     writeln!(f, "}}")?;
     Ok(())
 }
 
-fn table_fields(f: &mut dyn Write, fields: &[&str]) -> std::io::Result<()> {
+/// Legend row for inheritance arrows, if the diagram has any.
+fn inheritance_legend(inherits: &[Inherit]) -> Option<(String, &'static str)> {
+    (!inherits.is_empty()).then(|| {
+        (
+            "&#9651;".to_owned(),
+            "hollow arrow: the entity is a kind of the one it points at",
+        )
+    })
+}
+
+/// Box of `(symbol, meaning)` rows, in a cluster of its own so it is laid out
+/// apart from the entities.
+fn emit_legend(f: &mut dyn Write, rows: &[(String, &str)]) -> std::io::Result<()> {
+    writeln!(f, "subgraph cluster_legend {{\nlabel=\"Legend\";")?;
+    writeln!(
+        f,
+        r#"__legend [shape=plaintext, label=<
+            <TABLE border="0" cellborder="1" cellspacing="0">"#
+    )?;
+    for (symbol, meaning) in rows {
+        table_fields(f, &[symbol, meaning], "")?;
+    }
+    writeln!(f, "</TABLE> >];")?;
+    writeln!(f, "}}")
+}
+
+/// Default graph, node and edge attributes for the theme's colors and font.
+fn emit_theme(f: &mut dyn Write, theme: &Theme) -> std::io::Result<()> {
+    if let Some(background) = theme.background {
+        writeln!(f, "graph [bgcolor=\"{}\"];", dot_escape(background))?;
+    }
+    let attrs: Vec<String> = [
+        ("color", theme.line_color),
+        ("fontcolor", theme.font_color),
+        ("fontname", theme.font_name.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some(format!("{name}=\"{}\"", dot_escape(value?))))
+    .collect();
+    if !attrs.is_empty() {
+        let attrs = attrs.join(", ");
+        for target in ["graph", "node", "edge"] {
+            writeln!(f, "{target} [{attrs}];")?;
+        }
+    }
+    Ok(())
+}
+
+fn emit_direction(f: &mut dyn Write, opts: &EmitOptions) -> std::io::Result<()> {
+    match opts.direction {
+        Some(direction) => writeln!(f, "rankdir={};", <&str>::from(direction)),
+        None => Ok(()),
+    }
+}
+
+/// Entities in drawing order, ungrouped ones first and then each group's,
+/// along with the group they belong to.
+fn clusters<'a>(defs: &'a [Def], groups: &'a [Group]) -> Vec<(Option<&'a Group>, Vec<&'a Def>)> {
+    let in_group = |group: &Group, def: &Def| group.defs.contains(&def.name);
+    let ungrouped = defs
+        .iter()
+        .filter(|def| !groups.iter().any(|group| in_group(group, def)))
+        .collect();
+    let grouped = groups.iter().map(|group| {
+        let defs = defs.iter().filter(|def| in_group(group, def)).collect();
+        (Some(group), defs)
+    });
+    std::iter::once((None, ungrouped)).chain(grouped).collect()
+}
+
+/// Starts the `cluster_{i}` subgraph boxing `group`'s entities.
+fn open_cluster(f: &mut dyn Write, i: usize, group: Option<&Group>) -> std::io::Result<()> {
+    match group {
+        Some(group) => writeln!(
+            f,
+            "subgraph cluster_{i} {{\nlabel=\"{}\";",
+            dot_escape(&group.name)
+        ),
+        None => Ok(()),
+    }
+}
+
+fn close_cluster(f: &mut dyn Write, group: Option<&Group>) -> std::io::Result<()> {
+    match group {
+        Some(_) => writeln!(f, "}}"),
+        None => Ok(()),
+    }
+}
+
+/// Hover text for a link's edges, spelling out its cardinalities.
+fn tooltip(link: &Link) -> String {
+    let mut text = format!(
+        "{}: {}, {}: {}",
+        link.from,
+        link.from_count.describe(),
+        link.to,
+        link.to_count.describe()
+    );
+    if let Some(label) = &link.label {
+        text = format!("{text} ({label})");
+    }
+    let text = dot_escape(&text);
+    format!("edgetooltip=\"{text}\", labeltooltip=\"{text}\"")
+}
+
+/// Edge attributes for a link's `@priority`. Low priority links don't take
+/// part in ranking at all.
+fn weight(link: &Link) -> &'static str {
+    match link.priority {
+        None => "",
+        Some(Priority::High) => ", weight=10",
+        Some(Priority::Low) => ", weight=0, constraint=false",
+    }
+}
+
+/// Edge attributes for a link's `@style` and `@color`, which win over the
+/// colors of `--color-participation`.
+fn line_style(link: &Link) -> String {
+    let mut attrs = String::new();
+    if let Some(style) = link.style {
+        attrs += &format!(", style={}", <&str>::from(style));
+    }
+    if let Some(color) = &link.color {
+        attrs += &format!(", color=\"{color}\"");
+    }
+    attrs
+}
+
+/// Puts an edge label on a box of the theme's label color so crossing edges
+/// don't run through it.
+fn label_box(text: &str, theme: &Theme) -> String {
+    format!(
+        r#"<TABLE border="0" cellborder="0" cellpadding="1" bgcolor="{}"><TR><TD>{text}</TD></TR></TABLE>"#,
+        theme.label
+    )
+}
+
+/// Strikes `text` through if it is deprecated.
+fn struck(text: &str, deprecated: &Option<String>) -> String {
+    match deprecated {
+        Some(_) => format!("<S>{text}</S>"),
+        None => text.to_owned(),
+    }
+}
+
+fn deprecation_text(reason: &str) -> String {
+    if reason.is_empty() {
+        "Deprecated".to_owned()
+    } else {
+        format!("Deprecated: {reason}")
+    }
+}
+
+/// Tooltip explaining why the entity `name` is deprecated, if it is.
+fn emit_deprecation(
+    f: &mut dyn Write,
+    name: &str,
+    deprecated: &Option<String>,
+) -> std::io::Result<()> {
+    match deprecated {
+        Some(reason) => {
+            let text = dot_escape(&deprecation_text(reason));
+            writeln!(f, "{} [tooltip=\"{text}\"];", node_id(name))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Table cell attributes with a field's `@doc` and why it is deprecated, if
+/// it is.
+fn cell_tooltip(field: &Field) -> String {
+    let deprecated = field.deprecated.as_deref().map(deprecation_text);
+    let text = match (&field.doc, deprecated) {
+        (None, None) => return String::new(),
+        (Some(doc), None) => doc.clone(),
+        (None, Some(deprecated)) => deprecated,
+        (Some(doc), Some(deprecated)) => format!("{doc} ({deprecated})"),
+    };
+    format!(r#" TOOLTIP="{}""#, escape_xml(&text))
+}
+
+/// Reference to an entity that is drawn elsewhere.
+fn emit_stub(f: &mut dyn Write, name: &str, title: &str) -> std::io::Result<()> {
+    let name = node_id(name);
+    writeln!(f, "{name} [label=<{title}>, shape=box, style=dashed];")
+}
+
+/// Escapes text for use inside Graphviz HTML-like labels.
+fn escape_html(s: &str) -> Cow<'_, str> {
+    if !s.contains(['&', '<', '>']) {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+    )
+}
+
+/// Escapes text for use in XML content and attribute values.
+fn escape_xml(s: &str) -> String {
+    escape_html(s).replace('"', "&quot;")
+}
+
+/// A dot identifier for `name`. Bare identifiers can't start with a digit
+/// or hold spaces and the like, so those are quoted.
+fn node_id(name: &str) -> Cow<'_, str> {
+    if is_identifier(name) && !name.starts_with(|c: char| c.is_ascii_digit()) {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("\"{}\"", dot_escape(name)))
+    }
+}
+
+/// Attribute overriding the shape of an entity's node, if it has `@shape`.
+fn shape_attr(def: &Def) -> String {
+    match &def.shape {
+        Some(shape) => format!(", shape=\"{}\"", dot_escape(shape)),
+        None => String::new(),
+    }
+}
+
+/// Escapes text for a double-quoted dot string.
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Footnote rows listing an entity's check constraints.
+fn emit_checks(f: &mut dyn Write, checks: &[String]) -> std::io::Result<()> {
+    for check in checks {
+        let check = escape_xml(check);
+        writeln!(
+            f,
+            r#"<TR><TD colspan="2" align="LEFT"><I>check ({check})</I></TD></TR>"#
+        )?;
+    }
+    Ok(())
+}
+
+/// Splits table rows into a key compartment and the rest when
+/// `--key-compartment` is set, dropping empty compartments. Rows are
+/// `(is_key, row)` pairs.
+fn compartments<T>(rows: impl Iterator<Item = (bool, T)>, opts: &EmitOptions) -> Vec<Vec<T>> {
+    if !opts.key_compartment {
+        return vec![rows.map(|(_, row)| row).collect()];
+    }
+    let (keys, rest): (Vec<_>, Vec<_>) = rows.partition(|(is_key, _)| *is_key);
+    [keys, rest]
+        .into_iter()
+        .map(|rows| rows.into_iter().map(|(_, row)| row).collect::<Vec<_>>())
+        .filter(|rows| !rows.is_empty())
+        .collect()
+}
+
+fn table_fields(f: &mut dyn Write, fields: &[&str], attrs: &str) -> std::io::Result<()> {
     writeln!(f, "<TR>")?;
     for field in fields {
-        writeln!(f, r#"<TD align="LEFT">{field}</TD>"#)?;
+        writeln!(f, r#"<TD align="LEFT"{attrs}>{field}</TD>"#)?;
     }
     writeln!(f, "</TR>")?;
     Ok(())
 }
 
-pub fn emit_orm(f: &mut dyn Write, Doc { links, defs }: &Doc) -> std::io::Result<()> {
-    writeln!(f, "digraph {{")?;
-    writeln!(f, "graph [layout=dot];")?;
-    writeln!(f, "node [shape=plaintext];")?;
-    let mut def_links: HashMap<&str, Vec<&Link>> =
+/// Groups links by the entity they start from, which is the one holding the
+/// foreign key. Entities are kept sorted so that walking the map gives the
+/// same output on every run.
+fn def_links<'a>(defs: &'a [Def], links: &'a [Link]) -> BTreeMap<&'a str, Vec<&'a Link>> {
+    let mut def_links: BTreeMap<&str, Vec<&Link>> =
         defs.iter().map(|def| (&*def.name, vec![])).collect();
     for link in links {
-        def_links.get_mut(&*link.from).unwrap().push(link)
+        def_links.entry(&link.from).or_default().push(link)
     }
-    let def_links = def_links;
-    for def in defs {
-        let name = def.name.as_str();
-        writeln!(
-            f,
-            r#"{name} [label=<
-            <TABLE border="1" ALIGN="LEFT" cellborder="0" cellspacing="0">
-            <TR><TD colspan="2" border="1">{name}</TD></TR>"#
-        )?;
-        // writeln!(f, r#"<TR><TD ALIGN="LEFT" BALIGN="LEFT">"#)?;
-        for Field { name, .. } in &def.fields {
-            let name = name.as_str();
-            table_fields(
+    def_links
+}
+
+/// Links of the entity `name` that give it a foreign key column.
+fn fk_links<'a>(def_links: &BTreeMap<&str, Vec<&'a Link>>, name: &str) -> Vec<&'a Link> {
+    def_links[name]
+        .iter()
+        .copied()
+        .filter(|link| link.has_fk())
+        .collect()
+}
+
+/// Whether the entity declares the foreign key of `link` as one of its
+/// fields, to spell out its type, rather than leaving it to be generated.
+fn declares_fk(def: &Def, link: &Link, links: &[Link], naming: &Naming) -> bool {
+    let fk = link.fk_name(links, naming);
+    def.fields.iter().any(|field| field.name == fk)
+}
+
+pub fn emit_orm(
+    f: &mut dyn Write,
+    Doc {
+        links,
+        defs,
+        groups,
+        inherits,
+        ..
+    }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    writeln!(f, "digraph {{")?;
+    if let Some(layout) = &opts.layout {
+        writeln!(f, "graph [layout={layout}];")?;
+    }
+    emit_direction(f, opts)?;
+    emit_theme(f, &opts.theme)?;
+    writeln!(f, "node [shape=plaintext];")?;
+    let def_links = def_links(defs, links);
+    for (i, (group, defs)) in clusters(defs, groups).into_iter().enumerate() {
+        open_cluster(f, i, group)?;
+        for def in defs {
+            let name = def.name.as_str();
+            let title = struck(&escape_html(&opts.entity_name(name)), &def.deprecated);
+            emit_deprecation(f, name, &def.deprecated)?;
+            if def.stub {
+                emit_stub(f, name, &title)?;
+                continue;
+            }
+            let id = node_id(name);
+            if opts.skeleton {
+                let shape = def.shape.as_ref().map_or("box".to_owned(), |shape| {
+                    format!("\"{}\"", dot_escape(shape))
+                });
+                writeln!(f, "{id} [label=<{title}>, shape={shape}];")?;
+                continue;
+            }
+            writeln!(
                 f,
-                &[
-                    match name {
-                        "id" => "pk",
-                        _ => "",
-                    },
-                    &format!("+{name}"),
-                ],
+                r#"{id} [label=<
+            <TABLE border="1" ALIGN="LEFT" cellborder="0" cellspacing="0">
+            <TR><TD colspan="2" border="1">{title}</TD></TR>"#
             )?;
+            // writeln!(f, r#"<TR><TD ALIGN="LEFT" BALIGN="LEFT">"#)?;
+            let fks = fk_links(&def_links, name);
+            let (declared, fks): (Vec<&Link>, Vec<&Link>) = fks
+                .into_iter()
+                .partition(|link| declares_fk(def, link, links, &opts.naming));
+            let fields = def
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let is_pk = def.is_pk(i, opts.first_field_pk, &opts.naming)
+                        || declared.iter().any(|link| {
+                            link.body.is_pk && link.fk_name(links, &opts.naming) == field.name
+                        });
+                    let key = match (is_pk, field.unique) {
+                        (true, _) => "pk",
+                        (false, true) => "U",
+                        (false, false) => "",
+                    };
+                    let nullable = if field.nullable { "?" } else { "" };
+                    let name = struck(
+                        &format!("+{}{nullable}", escape_html(&field.name)),
+                        &field.deprecated,
+                    );
+                    (is_pk, (key, name, cell_tooltip(field)))
+                })
+                .filter(|(is_pk, _)| *is_pk || !opts.keys_only);
+            let fks = fks.into_iter().map(|link| {
+                let key = if link.body.is_pk { "fk_pk" } else { "fk" };
+                let nullable = if fk_nullable(link) { "?" } else { "" };
+                (
+                    link.body.is_pk,
+                    (
+                        key,
+                        format!(
+                            "+{}{nullable}",
+                            escape_html(&link.fk_name(links, &opts.naming))
+                        ),
+                        String::new(),
+                    ),
+                )
+            });
+            for (i, compartment) in compartments(fields.chain(fks), opts)
+                .into_iter()
+                .enumerate()
+            {
+                if i > 0 {
+                    writeln!(f, "<HR/>")?;
+                }
+                for (key, name, attrs) in compartment {
+                    table_fields(f, &[key, &name], &attrs)?;
+                }
+            }
+            emit_checks(f, &def.checks)?;
+            writeln!(f, "</TABLE> >{}];", shape_attr(def))?;
+            // writeln!(f, "</TD></TR></TABLE> >];")?;
         }
-        for Link { to, body, .. } in &def_links[name] {
-            let name = format!("+{}_id", to.to_lowercase());
-
-            table_fields(f, &[if body.is_pk { "fk_pk" } else { "fk" }, &*name])?;
-        }
-        writeln!(f, "</TABLE> >];")?;
-        // writeln!(f, "</TD></TR></TABLE> >];")?;
+        close_cluster(f, group)?;
     }
-    for Link {
-        from, to_count, to, ..
-    } in links
-    {
+    for link in links {
+        let Link {
+            from, to_count, to, ..
+        } = link;
+        let color = opts.participation(*to_count);
+        let to_count = label_box(&opts.cardinality(*to_count), &opts.theme);
+        let tooltip = tooltip(link);
+        let weight = weight(link);
+        let line = line_style(link);
+        let (from, to) = (node_id(from), node_id(to));
+        if link.junction {
+            // Drawn as the relationship a junction table would implement.
+            let label = escape_html(link.label.as_deref().unwrap_or_default());
+            let from_count = label_box(&opts.cardinality(link.from_count), &opts.theme);
+            writeln!(
+                f,
+                "\t{from} -> {to} [dir=both, style=dashed, label=<{}>, taillabel=<{from_count}>, headlabel=<{to_count}>
+            labelangle=45 labeldistance=2.1 labelfloat=false {tooltip}{color}{weight}{line}];",
+                label_box(&label, &opts.theme)
+            )?;
+            continue;
+        }
+        // Self links loop on the right so they stay clear of the title.
+        let ports = if from == to {
+            "tailport=e, headport=e, "
+        } else {
+            ""
+        };
+        // Symmetric links have no foreign key to point from, so both ends
+        // get an arrow and a count.
+        let symmetric = if link.body.symmetric {
+            let from_count = label_box(&opts.cardinality(link.from_count), &opts.theme);
+            format!("dir=both, taillabel=<{from_count}>, ")
+        } else {
+            String::new()
+        };
         writeln!(
             f,
-            "\t{from} -> {to} [headlabel=<{to_count}>
-            labelangle=45 labeldistance=2.1];"
+            "\t{from} -> {to} [{symmetric}headlabel=<{to_count}>
+            labelangle=45 labeldistance=2.1 labelfloat=false {ports}{tooltip}{color}{weight}{line}];"
         )?;
     }
+    for inherit in inherits {
+        let (child, parent) = (node_id(&inherit.child), node_id(&inherit.parent));
+        writeln!(f, "\t{child} -> {parent} [arrowhead=empty];")?;
+    }
+    if opts.legend {
+        let mut rows = vec![
+            ("pk".to_owned(), "primary key"),
+            (
+                "fk".to_owned(),
+                "foreign key to the entity the arrow points at",
+            ),
+            (
+                "fk_pk".to_owned(),
+                "foreign key that is part of the primary key",
+            ),
+            ("U".to_owned(), "unique"),
+        ];
+        rows.extend(LinkN::ALL.map(|count| (opts.cardinality(count), count.describe())));
+        rows.extend(inheritance_legend(inherits));
+        emit_legend(f, &rows)?;
+    }
     writeln!(f, "}}")?;
     Ok(())
 }
+
+/// A field as written in `emit_json` output, for entities and links alike.
+fn field_json(field: &Field) -> Json {
+    Json::obj([
+        ("name", Json::str(&field.name)),
+        ("type", Json::str(&field.field_type)),
+        ("list", Json::Bool(field.list)),
+        (
+            "variants",
+            Json::Arr(field.variants.iter().map(Json::str).collect()),
+        ),
+        ("example", Json::from(field.example.as_deref())),
+        (
+            "order",
+            field.order.map_or(Json::Null, |n| Json::Int(n.into())),
+        ),
+        ("deprecated", Json::from(field.deprecated.as_deref())),
+        ("pk", Json::Bool(field.pk)),
+        ("nullable", Json::Bool(field.nullable)),
+        ("unique", Json::Bool(field.unique)),
+        ("default", Json::from(field.default.as_deref())),
+        ("doc", Json::from(field.doc.as_deref())),
+    ])
+}
+
+pub fn emit_json(
+    f: &mut dyn Write,
+    Doc {
+        links,
+        defs,
+        groups,
+        version,
+        includes,
+        inherits,
+        enums,
+    }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let enums = enums.iter().map(|decl| {
+        Json::obj([
+            ("name", Json::str(&decl.name)),
+            (
+                "variants",
+                Json::Arr(decl.variants.iter().map(Json::str).collect()),
+            ),
+        ])
+    });
+    let defs = defs.iter().map(|def| {
+        Json::obj([
+            ("name", Json::str(&def.name)),
+            ("kind", Json::from(def.kind.as_deref())),
+            ("shape", Json::from(def.shape.as_deref())),
+            ("deprecated", Json::from(def.deprecated.as_deref())),
+            ("stub", Json::Bool(def.stub)),
+            (
+                "fields",
+                Json::Arr(def.fields.iter().map(field_json).collect()),
+            ),
+            (
+                "checks",
+                Json::Arr(def.checks.iter().map(Json::str).collect()),
+            ),
+        ])
+    });
+    let links = links.iter().map(|link| {
+        Json::obj([
+            ("from", Json::str(&link.from)),
+            ("from_count", Json::str(<&str>::from(link.from_count))),
+            ("to", Json::str(&link.to)),
+            ("to_count", Json::str(<&str>::from(link.to_count))),
+            ("is_pk", Json::Bool(link.body.is_pk)),
+            ("symmetric", Json::Bool(link.body.symmetric)),
+            ("label", Json::from(link.label.as_deref())),
+            ("priority", Json::from(link.priority.map(<&str>::from))),
+            ("style", Json::from(link.style.map(<&str>::from))),
+            ("color", Json::from(link.color.as_deref())),
+            (
+                "fields",
+                Json::Arr(link.fields.iter().map(field_json).collect()),
+            ),
+            ("junction", Json::Bool(link.junction)),
+        ])
+    });
+    let groups = groups.iter().map(|group| {
+        Json::obj([
+            ("name", Json::str(&group.name)),
+            (
+                "defs",
+                Json::Arr(group.defs.iter().map(Json::str).collect()),
+            ),
+        ])
+    });
+    let inherits = inherits.iter().map(|inherit| {
+        Json::obj([
+            ("child", Json::str(&inherit.child)),
+            ("parent", Json::str(&inherit.parent)),
+        ])
+    });
+    Json::obj([
+        (
+            "version",
+            Json::Int(opts.json_version.unwrap_or(JSON_VERSION).into()),
+        ),
+        (
+            "grammar_version",
+            Json::from(version.map(|version| version.to_string()).as_deref()),
+        ),
+        (
+            "includes",
+            Json::Arr(includes.iter().map(Json::str).collect()),
+        ),
+        ("enums", Json::Arr(enums.collect())),
+        ("defs", Json::Arr(defs.collect())),
+        ("links", Json::Arr(links.collect())),
+        ("groups", Json::Arr(groups.collect())),
+        ("inherits", Json::Arr(inherits.collect())),
+    ])
+    .write(f)
+}
+
+fn es_type(field_type: &str) -> Json {
+    let es_type = match Scalar::of(field_type) {
+        Some(Scalar::String) => {
+            return Json::obj([
+                ("type", Json::str("text")),
+                (
+                    "fields",
+                    Json::obj([("keyword", Json::obj([("type", Json::str("keyword"))]))]),
+                ),
+            ]);
+        }
+        Some(Scalar::Int) => "long",
+        Some(Scalar::Float) => "double",
+        Some(Scalar::Bool) => "boolean",
+        Some(Scalar::Date | Scalar::Timestamp) => "date",
+        Some(Scalar::Id | Scalar::Uuid) | None => "keyword",
+    };
+    Json::obj([("type", Json::str(es_type))])
+}
+
+/// One create-index body per entity, keyed by index name.
+pub fn emit_es_mapping(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let def_links = def_links(defs, links);
+    let indices = defs.iter().filter(|def| !def.stub).map(|def| {
+        let fields = def
+            .fields
+            .iter()
+            .map(|field| (field.name.clone(), es_type(&field.field_type)));
+        let fks = fk_links(&def_links, &def.name)
+            .into_iter()
+            .filter(|link| !declares_fk(def, link, links, &opts.naming))
+            .map(|link| (link.fk_name(links, &opts.naming), es_type("id")));
+        let properties = Json::Obj(fields.chain(fks).collect());
+        let mappings = Json::obj([("properties", properties)]);
+        let index = identifier(&opts.entity_name(&def.name).to_lowercase()).into_owned();
+        (index, Json::obj([("mappings", mappings)]))
+    });
+    Json::Obj(indices.collect()).write(f)
+}
+
+/// Name of the type declared for an enum field: the named enum it is typed
+/// after, or its entity and field like `OrderStatus` for an inline `enum(...)`.
+fn enum_name(def: &Def, field: &Field, opts: &EmitOptions) -> String {
+    if field.is_named_enum() {
+        opts.type_name(&field.field_type)
+    } else {
+        format!(
+            "{}{}",
+            opts.type_name(&def.name),
+            field.name.to_pascal_case()
+        )
+    }
+}
+
+/// Enum types of the entities in the output, by name, with their values.
+fn enum_types<'a>(defs: &'a [Def], opts: &EmitOptions) -> Vec<(String, &'a [String])> {
+    let mut enums: Vec<(String, &[String])> = vec![];
+    for def in defs.iter().filter(|def| !def.stub) {
+        for field in def.fields.iter().filter(|field| field.is_enum()) {
+            let name = enum_name(def, field, opts);
+            if !enums.iter().any(|(other, _)| *other == name) {
+                enums.push((name, &field.variants));
+            }
+        }
+    }
+    enums
+}
+
+/// GraphQL type of a field, or `None` for types that have to be declared as
+/// custom scalars.
+fn graphql_type(field_type: &str, defs: &[Def], opts: &EmitOptions) -> Option<String> {
+    Some(
+        match Scalar::of(field_type) {
+            Some(Scalar::Id | Scalar::Uuid) => "ID",
+            Some(Scalar::Int) => "Int",
+            Some(Scalar::Float) => "Float",
+            Some(Scalar::Bool) => "Boolean",
+            // GraphQL has no date type, dates are sent as ISO 8601 strings.
+            Some(Scalar::String | Scalar::Date | Scalar::Timestamp) => "String",
+            None if defs.iter().any(|def| def.name == field_type) => {
+                return Some(opts.type_name(field_type));
+            }
+            None => return None,
+        }
+        .to_owned(),
+    )
+}
+
+/// One GraphQL object type per entity. Links become fields holding the other
+/// type, a `[Other!]!` list for `n` and `n?` and nullable for `1?`. Unknown
+/// field types are declared as custom scalars.
+pub fn emit_graphql(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(2);
+    let is_stub = |name: &str| defs.iter().any(|def| def.stub && def.name == name);
+    let defs_shown = || defs.iter().filter(|def| !def.stub);
+    let mut scalars: Vec<String> = defs_shown()
+        .flat_map(|def| &def.fields)
+        .filter(|field| !field.is_enum() && graphql_type(&field.field_type, defs, opts).is_none())
+        .map(|field| field.field_type.to_pascal_case())
+        .collect();
+    scalars.sort();
+    scalars.dedup();
+    let mut sep = "";
+    for scalar in &scalars {
+        writeln!(f, "scalar {scalar}")?;
+        sep = "\n";
+    }
+    for (name, variants) in enum_types(defs, opts) {
+        write!(f, "{sep}")?;
+        sep = "\n";
+        writeln!(f, "enum {name} {{")?;
+        for variant in variants {
+            writeln!(f, "{indent}{}", identifier(variant))?;
+        }
+        writeln!(f, "}}")?;
+    }
+    let deprecated_def = |name: &str| defs.iter().find(|def| def.name == name)?.deprecated.clone();
+    for def in defs_shown() {
+        write!(f, "{sep}")?;
+        sep = "\n";
+        // Only fields can be deprecated in GraphQL, so are those linking here.
+        if let Some(reason) = &def.deprecated {
+            writeln!(f, "# {}", deprecation_text(reason))?;
+        }
+        writeln!(f, "type {} {{", opts.type_name(&def.name))?;
+        let mut empty = true;
+        for field in &def.fields {
+            let mut ty = if field.is_enum() {
+                enum_name(def, field, opts)
+            } else {
+                graphql_type(&field.field_type, defs, opts)
+                    .unwrap_or_else(|| field.field_type.to_pascal_case())
+            };
+            if field.list {
+                ty = format!("[{ty}!]");
+            }
+            let non_null = if field.nullable { "" } else { "!" };
+            let deprecated = graphql_deprecated(&field.deprecated);
+            writeln!(f, "{indent}{}: {ty}{non_null}{deprecated}", field.name)?;
+            empty = false;
+        }
+        for link in &def_links[&*def.name] {
+            let other = opts.type_name(&link.to);
+            if is_stub(&link.to) {
+                writeln!(
+                    f,
+                    "{indent}# {}: {other}, which is not part of this output",
+                    link.fk_stem(links)
+                )?;
+                continue;
+            }
+            let ty = match link.to_count {
+                LinkN::One => format!("{other}!"),
+                LinkN::MaybeOne => other,
+                LinkN::Many | LinkN::MaybeMany => format!("[{other}!]!"),
+            };
+            let name = graphql_link_field(link, links);
+            let deprecated = graphql_deprecated(&deprecated_def(&link.to));
+            writeln!(f, "{indent}{name}: {ty}{deprecated}")?;
+            empty = false;
+        }
+        // Object types need at least one field.
+        if empty {
+            writeln!(f, "{indent}_empty: Boolean")?;
+        }
+        writeln!(f, "}}")?;
+    }
+    Ok(())
+}
+
+/// Name of the GraphQL field a link adds to its entity.
+fn graphql_link_field(link: &Link, links: &[Link]) -> String {
+    if link.to_count.is_many() {
+        plural(&link.fk_stem(links))
+    } else {
+        link.fk_stem(links)
+    }
+}
+
+/// Language of the resolver stubs `--with-resolvers` writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::EnumString)]
+pub enum ResolverLang {
+    /// Plain TypeScript resolver maps, as Apollo Server takes them.
+    #[strum(serialize = "ts", serialize = "typescript")]
+    Ts,
+    /// `#[ComplexObject]` impls for async-graphql.
+    #[strum(serialize = "rust")]
+    Rust,
+}
+
+impl ResolverLang {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ResolverLang::Ts => "ts",
+            ResolverLang::Rust => "rs",
+        }
+    }
+}
+
+/// Relationship fields of the GraphQL type of `def`, with their links,
+/// leaving out those to entities outside of the output.
+fn graphql_relations<'a>(
+    def: &Def,
+    def_links: &BTreeMap<&str, Vec<&'a Link>>,
+    links: &[Link],
+    defs: &[Def],
+) -> Vec<(String, &'a Link)> {
+    def_links[&*def.name]
+        .iter()
+        .filter(|link| !defs.iter().any(|def| def.stub && def.name == link.to))
+        .map(|link| (graphql_link_field(link, links), *link))
+        .collect()
+}
+
+/// Stub resolvers for the relationship fields of the schema `-m graphql`
+/// writes, to fill in with the actual data loading.
+pub fn emit_resolvers(
+    f: &mut dyn Write,
+    doc: &Doc,
+    opts: &EmitOptions,
+    lang: ResolverLang,
+) -> std::io::Result<()> {
+    match lang {
+        ResolverLang::Ts => emit_ts_resolvers(f, doc, opts),
+        ResolverLang::Rust => emit_rust_resolvers(f, doc, opts),
+    }
+}
+
+/// The TypeScript interfaces of the entities, which resolvers get as their
+/// parent, and a resolver map typed after the links' cardinalities.
+fn emit_ts_resolvers(f: &mut dyn Write, doc: &Doc, opts: &EmitOptions) -> std::io::Result<()> {
+    let Doc { links, defs, .. } = doc;
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(2);
+    emit_ts(f, doc, opts)?;
+    writeln!(
+        f,
+        "
+export const resolvers = {{"
+    )?;
+    for def in defs.iter().filter(|def| !def.stub) {
+        let relations = graphql_relations(def, &def_links, links, defs);
+        if relations.is_empty() {
+            continue;
+        }
+        let parent = opts.type_name(&def.name);
+        writeln!(f, "{indent}{parent}: {{")?;
+        for (name, link) in relations {
+            let other = opts.type_name(&link.to);
+            let ty = match link.to_count {
+                LinkN::One => other,
+                LinkN::MaybeOne => format!("{other} | null"),
+                LinkN::Many | LinkN::MaybeMany => format!("{other}[]"),
+            };
+            let body = indent.repeat(3);
+            writeln!(
+                f,
+                "{indent}{indent}async {name}(parent: {parent}): Promise<{ty}> {{"
+            )?;
+            writeln!(f, "{body}// TODO: load the {name} of this {parent}")?;
+            writeln!(
+                f,
+                "{body}throw new Error({});",
+                json::quote(&format!("Not implemented: {parent}.{name}"))
+            )?;
+            writeln!(f, "{indent}{indent}}},")?;
+        }
+        writeln!(f, "{indent}}},")?;
+    }
+    writeln!(f, "}};")
+}
+
+fn rust_type(field_type: &str, defs: &[Def], opts: &EmitOptions) -> String {
+    match Scalar::of(field_type) {
+        Some(Scalar::Id | Scalar::Uuid) => "ID",
+        Some(Scalar::Int) => "i32",
+        Some(Scalar::Float) => "f64",
+        Some(Scalar::Bool) => "bool",
+        Some(Scalar::String | Scalar::Date | Scalar::Timestamp) => "String",
+        None if defs.iter().any(|def| def.name == field_type) => {
+            return opts.type_name(field_type);
+        }
+        // Custom scalars need an implementation of their own.
+        None => "String",
+    }
+    .to_owned()
+}
+
+/// async-graphql objects for the entities, holding their fields and foreign
+/// keys, with a `#[ComplexObject]` resolving their relationship fields.
+fn emit_rust_resolvers(f: &mut dyn Write, doc: &Doc, opts: &EmitOptions) -> std::io::Result<()> {
+    let Doc { links, defs, .. } = doc;
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(4);
+    let shown = || defs.iter().filter(|def| !def.stub);
+    let enums = enum_types(defs, opts);
+    let complex = shown().any(|def| !graphql_relations(def, &def_links, links, defs).is_empty());
+    let mut imports = vec![];
+    if complex {
+        imports.extend(["ComplexObject", "Context"]);
+    }
+    if !enums.is_empty() {
+        imports.push("Enum");
+    }
+    imports.push("ID");
+    if complex {
+        imports.push("Result");
+    }
+    imports.push("SimpleObject");
+    writeln!(f, "use async_graphql::{{{}}};", imports.join(", "))?;
+    for (name, variants) in &enums {
+        writeln!(
+            f,
+            "
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]"
+        )?;
+        writeln!(f, "pub enum {name} {{")?;
+        for variant in *variants {
+            // Named like the values of the GraphQL schema.
+            writeln!(
+                f,
+                "{indent}#[graphql(name = {})]",
+                json::quote(&identifier(variant))
+            )?;
+            writeln!(f, "{indent}{},", variant.to_pascal_case())?;
+        }
+        writeln!(f, "}}")?;
+    }
+    for def in shown() {
+        let relations = graphql_relations(def, &def_links, links, defs);
+        let name = opts.type_name(&def.name);
+        writeln!(
+            f,
+            "
+#[derive(SimpleObject)]"
+        )?;
+        if relations.is_empty() {
+            writeln!(f, "#[graphql(rename_fields = \"snake_case\")]")?;
+        } else {
+            writeln!(f, "#[graphql(complex, rename_fields = \"snake_case\")]")?;
+        }
+        writeln!(f, "pub struct {name} {{")?;
+        for field in &def.fields {
+            let mut ty = if field.is_enum() {
+                enum_name(def, field, opts)
+            } else {
+                rust_type(&field.field_type, defs, opts)
+            };
+            if field.list {
+                ty = format!("Vec<{ty}>");
+            }
+            if field.nullable {
+                ty = format!("Option<{ty}>");
+            }
+            writeln!(f, "{indent}pub {}: {ty},", identifier(&field.name))?;
+        }
+        // Foreign keys are only there for the resolvers to load the rows.
+        for link in fk_links(&def_links, &def.name) {
+            if declares_fk(def, link, links, &opts.naming) {
+                continue;
+            }
+            let ty = rust_type(pk_type(defs, &link.to, opts).unwrap_or("id"), defs, opts);
+            let ty = match link.to_count {
+                LinkN::One => ty,
+                LinkN::MaybeOne => format!("Option<{ty}>"),
+                LinkN::Many | LinkN::MaybeMany => format!("Vec<{ty}>"),
+            };
+            writeln!(f, "{indent}#[graphql(skip)]")?;
+            writeln!(
+                f,
+                "{indent}pub {}: {ty},",
+                link.fk_name(links, &opts.naming)
+            )?;
+        }
+        writeln!(f, "}}")?;
+        if relations.is_empty() {
+            continue;
+        }
+        writeln!(
+            f,
+            "
+#[ComplexObject]"
+        )?;
+        writeln!(f, "impl {name} {{")?;
+        for (i, (field, link)) in relations.iter().enumerate() {
+            let other = opts.type_name(&link.to);
+            let ty = match link.to_count {
+                LinkN::One => other,
+                LinkN::MaybeOne => format!("Option<{other}>"),
+                LinkN::Many | LinkN::MaybeMany => format!("Vec<{other}>"),
+            };
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "{indent}async fn {field}(&self, _ctx: &Context<'_>) -> Result<{ty}> {{"
+            )?;
+            writeln!(
+                f,
+                "{indent}{indent}todo!(\"load the {field} of this {name}\")"
+            )?;
+            writeln!(f, "{indent}}}")?;
+        }
+        writeln!(f, "}}")?;
+    }
+    Ok(())
+}
+
+/// GraphQL directive deprecating a field, with a space before it, if it is.
+fn graphql_deprecated(deprecated: &Option<String>) -> String {
+    match deprecated.as_deref() {
+        None => String::new(),
+        Some("") => " @deprecated".to_owned(),
+        Some(reason) => format!(" @deprecated(reason: {})", json::quote(reason)),
+    }
+}
+
+/// Naive English plural of a name. Names ending in an `s` that isn't part of
+/// `ss`, `us` or `is`, like the label `friends`, are taken to be plural already.
+fn plural(name: &str) -> String {
+    if name.ends_with('s') && !["ss", "us", "is"].iter().any(|end| name.ends_with(end)) {
+        name.to_owned()
+    } else if name.ends_with(['s', 'x', 'z']) || name.ends_with("ch") || name.ends_with("sh") {
+        format!("{name}es")
+    } else if let Some(stem) = name.strip_suffix('y')
+        && !stem.ends_with(['a', 'e', 'i', 'o', 'u'])
+    {
+        format!("{stem}ies")
+    } else {
+        format!("{name}s")
+    }
+}
+
+/// `count` instances of `name`, e.g. "one or more Products".
+fn quantity(count: LinkN, name: &str, is_self: bool) -> String {
+    let other = if is_self { "other " } else { "" };
+    let name = if count.is_many() {
+        plural(name)
+    } else {
+        name.to_owned()
+    };
+    format!("{} {other}{name}", count.describe())
+}
+
+/// Describes every relationship as a pair of English sentences.
+pub fn emit_prose(
+    f: &mut dyn Write,
+    Doc { links, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    for Link {
+        from,
+        from_count,
+        to_count,
+        to,
+        label,
+        ..
+    } in links
+    {
+        let is_self = from == to;
+        let (from, to) = (opts.entity_name(from), opts.entity_name(to));
+        let forward = quantity(*to_count, &to, is_self);
+        let backward = quantity(*from_count, &from, is_self);
+        let through = match label {
+            Some(label) => format!(" through \"{label}\""),
+            None => String::new(),
+        };
+        writeln!(
+            f,
+            "Each {from} is related to {forward}{through}, and each {to} to {backward}."
+        )?;
+    }
+    Ok(())
+}
+
+/// Lists every entity with its fields and relationships as a nested Markdown
+/// list, for reading linearly or with a screen reader.
+pub fn emit_outline(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    for def in defs {
+        let title = opts.entity_name(&def.name);
+        if def.stub {
+            writeln!(f, "- {title} (shown elsewhere)")?;
+            continue;
+        }
+        let deprecated = if def.deprecated.is_some() {
+            " (deprecated)"
+        } else {
+            ""
+        };
+        writeln!(f, "- {title}{deprecated}")?;
+        if !def.fields.is_empty() {
+            writeln!(f, "  - Fields")?;
+        }
+        for (i, field) in def.fields.iter().enumerate() {
+            let mut notes = vec![field.type_name().into_owned()];
+            if def.is_pk(i, opts.first_field_pk, &opts.naming) {
+                notes.push("primary key".to_owned());
+            }
+            if field.deprecated.is_some() {
+                notes.push("deprecated".to_owned());
+            }
+            writeln!(f, "    - {} ({})", field.name, notes.join(", "))?;
+        }
+        let related: Vec<String> = links
+            .iter()
+            .flat_map(|link| {
+                let is_self = link.from == link.to;
+                let forward = (link.from == def.name).then_some((link.to_count, &link.to));
+                let backward =
+                    (link.to == def.name && !is_self).then_some((link.from_count, &link.from));
+                forward
+                    .into_iter()
+                    .chain(backward)
+                    .map(move |(count, other)| {
+                        let through = match &link.label {
+                            Some(label) => format!(" through \"{label}\""),
+                            None => String::new(),
+                        };
+                        let other = quantity(count, &opts.entity_name(other), is_self);
+                        format!("related to {other}{through}")
+                    })
+            })
+            .collect();
+        if !related.is_empty() {
+            writeln!(f, "  - Relationships")?;
+        }
+        for line in related {
+            writeln!(f, "    - {line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Quotes `s` as a DiaLang literal. Numbers are written bare.
+fn source_literal(s: &str) -> String {
+    let is_digits = |p: &str| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit());
+    let number = s.strip_prefix('-').unwrap_or(s);
+    let is_number = match number.split_once('.') {
+        Some((int, frac)) => is_digits(int) && is_digits(frac),
+        None => is_digits(number),
+    };
+    if is_number {
+        return s.to_owned();
+    }
+    source_string(s)
+}
+
+fn source_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+fn source_deprecated(reason: &str) -> String {
+    if reason.is_empty() {
+        "@deprecated".to_owned()
+    } else {
+        format!("@deprecated {}", source_string(reason))
+    }
+}
+
+fn source_fields(f: &mut dyn Write, fields: &[Field], checks: &[String]) -> std::io::Result<()> {
+    writeln!(f, "{{")?;
+    // Types, names and annotations each line up in a column.
+    let names: Vec<String> = fields
+        .iter()
+        .map(|field| format!("{}{}", field.name, if field.nullable { "?" } else { "" }))
+        .collect();
+    let types: Vec<Cow<str>> = fields.iter().map(source_type).collect();
+    let type_width = types.iter().map(|ty| ty.len()).max().unwrap_or(0);
+    let name_width = names.iter().map(String::len).max().unwrap_or(0);
+    for ((field, name), ty) in fields.iter().zip(&names).zip(&types) {
+        let mut line = format!("    {ty:<type_width$} {name:<name_width$}");
+        if let Some(example) = &field.example {
+            line += &format!(" example {}", source_literal(example));
+        }
+        if let Some(order) = field.order {
+            line += &format!(" @order {order}");
+        }
+        if let Some(reason) = &field.deprecated {
+            line += &format!(" {}", source_deprecated(reason));
+        }
+        if field.pk {
+            line += " @pk";
+        }
+        if field.unique {
+            line += " @unique";
+        }
+        if let Some(default) = &field.default {
+            line += &format!(" @default {}", source_name(default));
+        }
+        if let Some(doc) = &field.doc {
+            line += &format!(" @doc {}", source_name(doc));
+        }
+        writeln!(f, "{}", line.trim_end())?;
+    }
+    for check in checks {
+        writeln!(f, "    check ({check})")?;
+    }
+    writeln!(f, "}}")
+}
+
+/// Type of `field` as written in DiaLang source, listing the values of inline
+/// enums.
+fn source_type(field: &Field) -> Cow<'_, str> {
+    if !field.is_enum() || field.is_named_enum() {
+        return field.type_name();
+    }
+    let variants: Vec<Cow<str>> = field.variants.iter().map(|v| source_name(v)).collect();
+    let list = if field.list { "[]" } else { "" };
+    Cow::Owned(format!("enum({}){list}", variants.join(", ")))
+}
+
+/// `name` as written in DiaLang source, quoted unless it's a bare name.
+fn source_name(name: &str) -> Cow<'_, str> {
+    if is_identifier(name) {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(source_string(name))
+    }
+}
+
+fn source_def(f: &mut dyn Write, def: &Def) -> std::io::Result<()> {
+    write!(f, "{}", source_name(&def.name))?;
+    if let Some(kind) = &def.kind {
+        write!(f, " @kind {kind}")?;
+    }
+    if let Some(shape) = &def.shape {
+        write!(f, " @shape {}", source_name(shape))?;
+    }
+    if let Some(reason) = &def.deprecated {
+        write!(f, " {}", source_deprecated(reason))?;
+    }
+    write!(f, " ")?;
+    source_fields(f, &def.fields, &def.checks)
+}
+
+/// Writes the document back as DiaLang source.
+pub fn emit_source(f: &mut dyn Write, doc: &Doc) -> std::io::Result<()> {
+    let mut sep = "";
+    if let Some(version) = doc.version {
+        writeln!(f, "dialang {version}")?;
+        sep = "\n";
+    }
+    if !doc.includes.is_empty() {
+        write!(f, "{sep}")?;
+        for include in &doc.includes {
+            writeln!(f, "include {}", source_string(include))?;
+        }
+        sep = "\n";
+    }
+    if !doc.enums.is_empty() {
+        write!(f, "{sep}")?;
+        for decl in &doc.enums {
+            let variants: Vec<Cow<str>> = decl.variants.iter().map(|v| source_name(v)).collect();
+            writeln!(
+                f,
+                "enum {} {{ {} }}",
+                source_name(&decl.name),
+                variants.join(", ")
+            )?;
+        }
+        sep = "\n";
+    }
+    for def in doc
+        .defs
+        .iter()
+        .filter(|def| doc.group_of(&def.name).is_none())
+    {
+        write!(f, "{sep}")?;
+        source_def(f, def)?;
+        sep = "\n";
+    }
+    for group in &doc.groups {
+        writeln!(f, "{sep}group {} {{", source_string(&group.name))?;
+        let mut inner = vec![];
+        let mut def_sep = "";
+        for def in group.defs.iter().filter_map(|name| doc.def(name)) {
+            write!(inner, "{def_sep}")?;
+            source_def(&mut inner, def)?;
+            def_sep = "\n";
+        }
+        for line in String::from_utf8_lossy(&inner).lines() {
+            match line {
+                "" => writeln!(f)?,
+                line => writeln!(f, "    {line}")?,
+            }
+        }
+        writeln!(f, "}}")?;
+        sep = "\n";
+    }
+    if !doc.inherits.is_empty() {
+        write!(f, "{sep}")?;
+        for inherit in &doc.inherits {
+            writeln!(
+                f,
+                "{} :> {}",
+                source_name(&inherit.child),
+                source_name(&inherit.parent)
+            )?;
+        }
+        sep = "\n";
+    }
+    if !doc.links.is_empty() {
+        write!(f, "{sep}")?;
+    }
+    for link in &doc.links {
+        let body = match (link.body.is_pk, link.body.symmetric) {
+            (true, _) => "==",
+            (false, true) => "<->",
+            (false, false) => "--",
+        };
+        write!(
+            f,
+            "{} {}{body}{} {}",
+            source_name(&link.from),
+            link.from_count.source(),
+            link.to_count.source(),
+            source_name(&link.to)
+        )?;
+        if let Some(label) = &link.label {
+            write!(f, ": {}", source_name(label))?;
+        }
+        if let Some(priority) = link.priority {
+            write!(f, " @priority {}", <&str>::from(priority))?;
+        }
+        if let Some(style) = link.style {
+            write!(f, " @style {}", <&str>::from(style))?;
+        }
+        if let Some(color) = &link.color {
+            write!(f, " @color {}", source_name(color))?;
+        }
+        if link.fields.is_empty() {
+            writeln!(f)?;
+        } else {
+            write!(f, " ")?;
+            source_fields(f, &link.fields, &[])?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the foreign key column of `link` may be null: when the link is
+/// optional on the referenced side, unless the column is part of the primary
+/// key.
+fn fk_nullable(link: &Link) -> bool {
+    link.to_count.is_optional() && !link.body.is_pk
+}
+
+/// Primary key of the entity called `name`, if it has a single one.
+fn pk_field<'a>(defs: &'a [Def], name: &str, opts: &EmitOptions) -> Option<&'a Field> {
+    let def = defs.iter().find(|def| def.name == name)?;
+    let mut pks =
+        (0..def.fields.len()).filter(|&i| def.is_pk(i, opts.first_field_pk, &opts.naming));
+    match (pks.next(), pks.next()) {
+        (Some(i), None) => Some(&def.fields[i]),
+        _ => None,
+    }
+}
+
+/// Type of the primary key of the entity called `name`, if it has a single one.
+fn pk_type<'a>(defs: &'a [Def], name: &str, opts: &EmitOptions) -> Option<&'a str> {
+    pk_field(defs, name, opts).map(|field| field.field_type.as_str())
+}
+
+/// Column foreign keys to the entity called `name` reference: its primary
+/// key, or the one `--pk-name` would give it.
+fn pk_column(defs: &[Def], name: &str, opts: &EmitOptions) -> String {
+    pk_field(defs, name, opts).map_or_else(|| opts.naming.pk_name(name), |pk| pk.name.clone())
+}
+
+/// Python keywords, and the soft keywords that are only reserved in some
+/// places, which can't be used as names.
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "case", "class",
+    "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if",
+    "import", "in", "is", "lambda", "match", "nonlocal", "not", "or", "pass", "raise", "return",
+    "try", "type", "while", "with", "yield", "_",
+];
+
+/// `name` as a Python identifier: keywords get a trailing `_` and names
+/// starting with a digit an `n_` prefix, since a leading `_` would make
+/// Pydantic take fields for private attributes.
+fn python_name(name: &str) -> Cow<'_, str> {
+    let name = identifier(name);
+    if PYTHON_KEYWORDS.contains(&name.as_ref()) {
+        Cow::Owned(format!("{name}_"))
+    } else if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        Cow::Owned(format!("n_{name}"))
+    } else {
+        name
+    }
+}
+
+fn python_type(field_type: &str, defs: &[Def], opts: &EmitOptions) -> String {
+    match Scalar::of(field_type) {
+        Some(Scalar::Id | Scalar::Int) => "int",
+        Some(Scalar::String) => "str",
+        Some(Scalar::Float) => "float",
+        Some(Scalar::Bool) => "bool",
+        Some(Scalar::Date) => "date",
+        Some(Scalar::Timestamp) => "datetime",
+        Some(Scalar::Uuid) => "UUID",
+        None if defs.iter().any(|def| def.name == field_type) => {
+            return python_name(&opts.type_name(field_type)).into_owned();
+        }
+        None => "Any",
+    }
+    .to_owned()
+}
+
+/// One Pydantic model per entity. Links become `<other>_id` fields. Names
+/// that aren't Python identifiers are changed, with fields keeping theirs as
+/// an alias.
+pub fn emit_pydantic(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    writeln!(f, "from __future__ import annotations")?;
+    writeln!(f)?;
+    writeln!(f, "from datetime import date, datetime")?;
+    writeln!(f, "from enum import Enum")?;
+    writeln!(f, "from typing import Any, Optional")?;
+    writeln!(f, "from uuid import UUID")?;
+    writeln!(f)?;
+    let renamed = |field: &Field| python_name(&field.name) != field.name.as_str();
+    if defs
+        .iter()
+        .any(|def| !def.stub && def.fields.iter().any(renamed))
+    {
+        writeln!(f, "from pydantic import BaseModel, Field")?;
+    } else {
+        writeln!(f, "from pydantic import BaseModel")?;
+    }
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(4);
+    for (name, variants) in enum_types(defs, opts) {
+        writeln!(f, "\n\nclass {}(str, Enum):", python_name(&name))?;
+        for variant in variants {
+            writeln!(
+                f,
+                "{indent}{} = {}",
+                python_name(variant),
+                json::quote(variant)
+            )?;
+        }
+    }
+    for def in defs.iter().filter(|def| !def.stub) {
+        let model = opts.type_name(&def.name);
+        writeln!(f, "\n\nclass {}(BaseModel):", python_name(&model))?;
+        for field in &def.fields {
+            let mut ty = if field.is_enum() {
+                python_name(&enum_name(def, field, opts)).into_owned()
+            } else {
+                python_type(&field.field_type, defs, opts)
+            };
+            if field.list {
+                ty = format!("list[{ty}]");
+            }
+            let literal = field.default.as_deref().and_then(Literal::parse);
+            let name = python_name(&field.name);
+            let mut line = if field.nullable {
+                format!("{indent}{name}: Optional[{ty}]")
+            } else {
+                format!("{indent}{name}: {ty}")
+            };
+            let default = match &literal {
+                Some(literal) => Some(literal.python()),
+                None if field.nullable => Some("None".to_owned()),
+                None => None,
+            };
+            if name != field.name.as_str() {
+                let alias = json::quote(&field.name);
+                match default {
+                    Some(default) => line += &format!(" = Field({default}, alias={alias})"),
+                    None => line += &format!(" = Field(alias={alias})"),
+                }
+            } else if let Some(default) = default {
+                line += &format!(" = {default}");
+            }
+            // Defaults computed by the database can't be filled in here.
+            if let (None, Some(default)) = (&literal, &field.default) {
+                line += &format!("  # default: {default}");
+            }
+            writeln!(f, "{line}")?;
+        }
+        let mut fks = fk_links(&def_links, &def.name);
+        fks.retain(|link| !declares_fk(def, link, links, &opts.naming));
+        for link in &fks {
+            let ty = python_type(pk_type(defs, &link.to, opts).unwrap_or("id"), defs, opts);
+            let fk = link.fk_name(links, &opts.naming);
+            let fk = python_name(&fk);
+            match link.to_count {
+                LinkN::One | LinkN::Many => writeln!(f, "{indent}{fk}: {ty}")?,
+                LinkN::MaybeOne | LinkN::MaybeMany => {
+                    writeln!(f, "{indent}{fk}: Optional[{ty}] = None")?
+                }
+            }
+        }
+        if def.fields.is_empty() && fks.is_empty() {
+            writeln!(f, "{indent}pass")?;
+        }
+    }
+    Ok(())
+}
+
+fn mongoose_type(field_type: &str, defs: &[Def], opts: &EmitOptions) -> String {
+    match Scalar::of(field_type) {
+        Some(Scalar::Id) => "mongoose.Schema.Types.ObjectId",
+        Some(Scalar::String | Scalar::Uuid) => "String",
+        Some(Scalar::Int | Scalar::Float) => "Number",
+        Some(Scalar::Bool) => "Boolean",
+        Some(Scalar::Date | Scalar::Timestamp) => "Date",
+        None if defs.iter().any(|def| def.name == field_type) => {
+            let model = opts.type_name(field_type);
+            return format!("mongoose.Schema.Types.ObjectId, ref: '{model}'");
+        }
+        None => "mongoose.Schema.Types.Mixed",
+    }
+    .to_owned()
+}
+
+pub fn emit_mongoose(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    writeln!(f, "const mongoose = require('mongoose');")?;
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(2);
+    let mut models = vec![];
+    for def in defs.iter().filter(|def| !def.stub) {
+        let model = opts.type_name(&def.name);
+        writeln!(f, "\nconst {model}Schema = new mongoose.Schema({{")?;
+        for field in &def.fields {
+            let mut ty = mongoose_type(&field.field_type, defs, opts);
+            if field.is_enum() {
+                let values: Vec<String> = field.variants.iter().map(|v| json::quote(v)).collect();
+                ty = format!("String, enum: [{}]", values.join(", "));
+            }
+            if field.list {
+                writeln!(f, "{indent}{}: [{{ type: {ty} }}],", field.name)?;
+                continue;
+            }
+            let mut options = format!("type: {ty}");
+            if !field.nullable {
+                options += ", required: true";
+            }
+            let literal = field.default.as_deref().and_then(Literal::parse);
+            if let Some(literal) = &literal {
+                options += &format!(", default: {}", literal.js());
+            }
+            let mut line = format!("{indent}{}: {{ {options} }},", field.name);
+            // Defaults computed by the database can't be filled in here.
+            if let (None, Some(default)) = (&literal, &field.default) {
+                line += &format!(" // default: {default}");
+            }
+            writeln!(f, "{line}")?;
+        }
+        for link in fk_links(&def_links, &def.name) {
+            if declares_fk(def, link, links, &opts.naming) {
+                continue;
+            }
+            let other = opts.type_name(&link.to);
+            let reference = format!("type: mongoose.Schema.Types.ObjectId, ref: '{other}'");
+            let name = link.fk_name(links, &opts.naming);
+            match link.to_count {
+                LinkN::One => writeln!(f, "{indent}{name}: {{ {reference}, required: true }},")?,
+                LinkN::MaybeOne => writeln!(f, "{indent}{name}: {{ {reference} }},")?,
+                LinkN::Many | LinkN::MaybeMany => {
+                    writeln!(f, "{indent}{}: [{{ {reference} }}],", plural(&name))?
+                }
+            }
+        }
+        writeln!(f, "}});")?;
+        models.push(model);
+    }
+    writeln!(f, "\nmodule.exports = {{")?;
+    for model in models {
+        writeln!(
+            f,
+            "{indent}{model}: mongoose.model('{model}', {model}Schema),"
+        )?;
+    }
+    writeln!(f, "}};")?;
+    Ok(())
+}
+
+pub fn emit_graphml(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        f,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns"
+    xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+    xsi:schemaLocation="http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd">"#
+    )?;
+    for (id, domain, ty) in [
+        ("name", "node", "string"),
+        ("kind", "node", "string"),
+        ("fields", "node", "string"),
+        ("label", "edge", "string"),
+        ("from_count", "edge", "string"),
+        ("to_count", "edge", "string"),
+        ("is_pk", "edge", "boolean"),
+    ] {
+        writeln!(
+            f,
+            r#"  <key id="{id}" for="{domain}" attr.name="{id}" attr.type="{ty}"/>"#
+        )?;
+    }
+    writeln!(f, r#"  <graph id="G" edgedefault="directed">"#)?;
+    for def in defs {
+        writeln!(f, r#"    <node id="{}">"#, escape_xml(&def.name))?;
+        let name = escape_xml(&opts.entity_name(&def.name));
+        writeln!(f, r#"      <data key="name">{name}</data>"#)?;
+        if let Some(kind) = &def.kind {
+            writeln!(f, r#"      <data key="kind">{}</data>"#, escape_xml(kind))?;
+        }
+        let fields: Vec<String> = def
+            .fields
+            .iter()
+            .map(|field| format!("{} {}", field.type_name(), field.name))
+            .collect();
+        writeln!(
+            f,
+            r#"      <data key="fields">{}</data>"#,
+            escape_xml(&fields.join("\n"))
+        )?;
+        writeln!(f, "    </node>")?;
+    }
+    for (i, link) in links.iter().enumerate() {
+        writeln!(
+            f,
+            r#"    <edge id="e{i}" source="{}" target="{}">"#,
+            escape_xml(&link.from),
+            escape_xml(&link.to)
+        )?;
+        if let Some(label) = &link.label {
+            writeln!(f, r#"      <data key="label">{}</data>"#, escape_xml(label))?;
+        }
+        let (from_count, to_count) = (
+            opts.cardinality(link.from_count),
+            opts.cardinality(link.to_count),
+        );
+        writeln!(
+            f,
+            r#"      <data key="from_count">{}</data>"#,
+            escape_xml(&from_count)
+        )?;
+        writeln!(
+            f,
+            r#"      <data key="to_count">{}</data>"#,
+            escape_xml(&to_count)
+        )?;
+        writeln!(f, r#"      <data key="is_pk">{}</data>"#, link.body.is_pk)?;
+        writeln!(f, "    </edge>")?;
+    }
+    writeln!(f, "  </graph>")?;
+    writeln!(f, "</graphml>")?;
+    Ok(())
+}
+
+/// `text` in Mermaid double quotes, which take `#quot;` instead of a
+/// backslash escape.
+fn mermaid_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "#quot;"))
+}
+
+pub fn emit_mermaid(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    writeln!(f, "erDiagram")?;
+    for def in defs {
+        let name = identifier(&def.name);
+        let title = opts.entity_name(&def.name);
+        let alias = if title == *name {
+            String::new()
+        } else {
+            format!("[{}]", mermaid_string(&title))
+        };
+        if def.fields.is_empty() || opts.skeleton {
+            writeln!(f, "    {name}{alias}")?;
+            continue;
+        }
+        writeln!(f, "    {name}{alias} {{")?;
+        for (i, field) in def.fields.iter().enumerate() {
+            let key = if def.is_pk(i, opts.first_field_pk, &opts.naming) {
+                " PK"
+            } else {
+                ""
+            };
+            writeln!(f, "        {} {}{key}", field.type_name(), field.name)?;
+        }
+        writeln!(f, "    }}")?;
+    }
+    for link in links {
+        use LinkN::*;
+        let left = match link.from_count {
+            One => "||",
+            MaybeOne => "|o",
+            Many => "}|",
+            MaybeMany => "}o",
+        };
+        let right = match link.to_count {
+            One => "||",
+            MaybeOne => "o|",
+            Many => "|{",
+            MaybeMany => "o{",
+        };
+        // Mermaid calls links whose foreign key is part of the primary key
+        // identifying, and draws the others dashed.
+        let line = if link.body.is_pk { "--" } else { ".." };
+        writeln!(
+            f,
+            "    {} {left}{line}{right} {} : {}",
+            identifier(&link.from),
+            identifier(&link.to),
+            mermaid_string(link.label.as_deref().unwrap_or_default())
+        )?;
+    }
+    Ok(())
+}
+
+fn ts_type(field_type: &str, defs: &[Def], opts: &EmitOptions) -> String {
+    match Scalar::of(field_type) {
+        Some(Scalar::Id | Scalar::Int | Scalar::Float) => "number",
+        // Dates travel as ISO 8601 strings in JSON.
+        Some(Scalar::String | Scalar::Uuid | Scalar::Date | Scalar::Timestamp) => "string",
+        Some(Scalar::Bool) => "boolean",
+        None if defs.iter().any(|def| def.name == field_type) => {
+            return opts.type_name(field_type);
+        }
+        None => "unknown",
+    }
+    .to_owned()
+}
+
+/// JSDoc comment with a `@deprecated` tag, which editors strike through.
+fn ts_deprecated(reason: &str) -> String {
+    if reason.is_empty() {
+        "/** @deprecated */".to_owned()
+    } else {
+        format!("/** @deprecated {} */", reason.replace("*/", "*\\/"))
+    }
+}
+
+/// One TypeScript interface per entity. Links become foreign key fields like
+/// `<other>_id`, or arrays like `<other>_ids` for `n` and `n?`.
+pub fn emit_ts(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(2);
+    let mut sep = "";
+    for (name, variants) in enum_types(defs, opts) {
+        let values: Vec<String> = variants.iter().map(|v| json::quote(v)).collect();
+        writeln!(f, "export type {name} = {};", values.join(" | "))?;
+        sep = "\n";
+    }
+    for def in defs.iter().filter(|def| !def.stub) {
+        write!(f, "{sep}")?;
+        sep = "\n";
+        if let Some(reason) = &def.deprecated {
+            writeln!(f, "{}", ts_deprecated(reason))?;
+        }
+        writeln!(f, "export interface {} {{", opts.type_name(&def.name))?;
+        for field in &def.fields {
+            let mut ty = if field.is_enum() {
+                enum_name(def, field, opts)
+            } else {
+                ts_type(&field.field_type, defs, opts)
+            };
+            if field.list {
+                ty += "[]";
+            }
+            let null = if field.nullable { " | null" } else { "" };
+            if let Some(reason) = &field.deprecated {
+                writeln!(f, "{indent}{}", ts_deprecated(reason))?;
+            }
+            writeln!(f, "{indent}{}: {ty}{null};", field.name)?;
+        }
+        for link in fk_links(&def_links, &def.name) {
+            if declares_fk(def, link, links, &opts.naming) {
+                continue;
+            }
+            let ty = ts_type(pk_type(defs, &link.to, opts).unwrap_or("id"), defs, opts);
+            let name = link.fk_name(links, &opts.naming);
+            match link.to_count {
+                LinkN::One => writeln!(f, "{indent}{name}: {ty};")?,
+                LinkN::MaybeOne => writeln!(f, "{indent}{name}?: {ty};")?,
+                LinkN::Many | LinkN::MaybeMany => {
+                    writeln!(f, "{indent}{}: {ty}[];", plural(&name))?
+                }
+            }
+        }
+        writeln!(f, "}}")?;
+    }
+    Ok(())
+}
+
+fn prisma_type(field_type: &str) -> String {
+    match Scalar::of(field_type) {
+        Some(Scalar::Id | Scalar::Int) => "Int",
+        Some(Scalar::String | Scalar::Uuid) => "String",
+        Some(Scalar::Float) => "Float",
+        Some(Scalar::Bool) => "Boolean",
+        Some(Scalar::Date | Scalar::Timestamp) => "DateTime",
+        None => return format!("Unsupported(\"{field_type}\")"),
+    }
+    .to_owned()
+}
+
+/// One Prisma model per entity. Links add a foreign key and a relation field
+/// to the entity holding the key and a back relation to the other one, and
+/// many-to-many links become implicit many-to-many relations.
+pub fn emit_prisma(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(2);
+    let is_stub = |name: &str| defs.iter().any(|def| def.stub && def.name == name);
+    let many_to_many =
+        |link: &Link| link.junction || (link.from_count.is_many() && link.to_count.is_many());
+    // Prisma needs relations between the same two models to be named.
+    let relation_name = |link: &Link| {
+        let same_pair = |other: &&Link| {
+            (other.from == link.from && other.to == link.to)
+                || (other.from == link.to && other.to == link.from)
+        };
+        if link.from == link.to || links.iter().filter(same_pair).count() > 1 {
+            let name = match &link.label {
+                Some(label) => label.clone(),
+                None => format!("{}To{}", link.from, link.to),
+            };
+            Some(format!("\"{}\"", dot_escape(&name)))
+        } else {
+            None
+        }
+    };
+    // Back relations, on the entity links point to.
+    let mut back: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for link in links {
+        if is_stub(&link.from) || is_stub(&link.to) || !(link.has_fk() || many_to_many(link)) {
+            continue;
+        }
+        let model = opts.type_name(&link.from);
+        let relation =
+            relation_name(link).map_or(String::new(), |name| format!(" @relation({name})"));
+        let list = many_to_many(link) || link.from_count.is_many();
+        // Labels name the back relation too, so links between the same two
+        // models get different fields.
+        let name = if link.from == link.to {
+            format!("{}_of", link.fk_stem(links))
+        } else if let Some(label) = &link.label {
+            format!("{}_of", identifier(&label.to_lowercase()))
+        } else if list {
+            plural(&identifier(&link.from.to_lowercase()))
+        } else {
+            identifier(&link.from.to_lowercase()).into_owned()
+        };
+        let field = if list {
+            format!("{name} {model}[]{relation}")
+        } else {
+            format!("{name} {model}?{relation}")
+        };
+        back.entry(&link.to).or_default().push(field);
+    }
+    let mut sep = "";
+    for (name, variants) in enum_types(defs, opts) {
+        write!(f, "{sep}")?;
+        sep = "\n";
+        writeln!(f, "enum {name} {{")?;
+        for variant in variants {
+            writeln!(f, "{indent}{}", identifier(variant))?;
+        }
+        writeln!(f, "}}")?;
+    }
+    for def in defs.iter().filter(|def| !def.stub) {
+        write!(f, "{sep}")?;
+        sep = "\n";
+        writeln!(f, "model {} {{", opts.type_name(&def.name))?;
+        let def_links = &def_links[&*def.name];
+        let mut pk: Vec<String> = (0..def.fields.len())
+            .filter(|&i| def.is_pk(i, opts.first_field_pk, &opts.naming))
+            .map(|i| def.fields[i].name.clone())
+            .collect();
+        pk.extend(
+            def_links
+                .iter()
+                .filter(|link| link.body.is_pk && !many_to_many(link))
+                .map(|link| link.fk_name(links, &opts.naming)),
+        );
+        let single_pk = |column: &str| pk.len() == 1 && pk[0] == column;
+        for field in &def.fields {
+            let ty = if field.is_enum() {
+                enum_name(def, field, opts)
+            } else {
+                prisma_type(&field.field_type)
+            };
+            let mut line = format!("{indent}{} {ty}", field.name);
+            if field.list {
+                line += "[]";
+            } else if field.nullable {
+                line += "?";
+            }
+            if single_pk(&field.name) {
+                line += " @id";
+            }
+            if field.unique {
+                line += " @unique";
+            }
+            if let Some(default) = &field.default {
+                line += &format!(" @default(dbgenerated(\"{}\"))", dot_escape(default));
+            }
+            writeln!(f, "{line}")?;
+        }
+        for link in def_links {
+            let model = opts.type_name(&link.to);
+            let relation = relation_name(link);
+            if !(link.has_fk() || many_to_many(link)) {
+                continue;
+            }
+            if many_to_many(link) {
+                let relation = relation.map_or(String::new(), |name| format!(" @relation({name})"));
+                writeln!(
+                    f,
+                    "{indent}{} {model}[]{relation}",
+                    plural(&link.fk_stem(links))
+                )?;
+                continue;
+            }
+            let column = link.fk_name(links, &opts.naming);
+            let ty = prisma_type(pk_type(defs, &link.to, opts).unwrap_or("id"));
+            let optional = if fk_nullable(link) { "?" } else { "" };
+            // One-to-one relations need the key to be unique.
+            let unique = if link.from_count.is_many() || single_pk(&column) {
+                ""
+            } else {
+                " @unique"
+            };
+            let id = if single_pk(&column) { " @id" } else { "" };
+            let declared = declares_fk(def, link, links, &opts.naming);
+            if is_stub(&link.to) {
+                writeln!(
+                    f,
+                    "{indent}// References {model}, which is not part of this output"
+                )?;
+                if !declared {
+                    writeln!(f, "{indent}{column} {ty}{optional}{id}{unique}")?;
+                }
+                continue;
+            }
+            if !declared {
+                writeln!(f, "{indent}{column} {ty}{optional}{id}{unique}")?;
+            }
+            let referenced = pk_column(defs, &link.to, opts);
+            let name = relation.map_or(String::new(), |name| format!("{name}, "));
+            writeln!(
+                f,
+                "{indent}{} {model}{optional} @relation({name}fields: [{column}], references: [{referenced}])",
+                link.fk_stem(links)
+            )?;
+        }
+        for field in back.get(&*def.name).into_iter().flatten() {
+            writeln!(f, "{indent}{field}")?;
+        }
+        if pk.len() > 1 {
+            writeln!(f, "\n{indent}@@id([{}])", pk.join(", "))?;
+        }
+        writeln!(f, "}}")?;
+    }
+    Ok(())
+}
+
+/// SQL column type for each scalar, anything else is written as declared.
+const SQL_TYPES: &[(Scalar, &str)] = &[
+    (Scalar::Id, "INTEGER"),
+    (Scalar::String, "TEXT"),
+    (Scalar::Int, "INTEGER"),
+    (Scalar::Float, "REAL"),
+    (Scalar::Bool, "BOOLEAN"),
+    (Scalar::Date, "DATE"),
+    (Scalar::Timestamp, "TIMESTAMP"),
+    (Scalar::Uuid, "UUID"),
+];
+
+fn sql_type(field_type: &str) -> &str {
+    let scalar = Scalar::of(field_type);
+    SQL_TYPES
+        .iter()
+        .find(|(s, _)| Some(*s) == scalar)
+        .map_or(field_type, |(_, sql)| sql)
+}
+
+/// SQL type of a field's column, an array for lists.
+fn sql_column_type(field: &Field) -> String {
+    let ty = if field.is_enum() {
+        "TEXT"
+    } else {
+        sql_type(&field.field_type)
+    };
+    if field.list {
+        format!("{ty}[]")
+    } else {
+        ty.to_owned()
+    }
+}
+
+fn sql_string(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// A `@default` that is a plain SQL literal, which other languages can write
+/// in their own syntax. Any other expression, like `now()`, only means
+/// something to a database.
+enum Literal<'a> {
+    Str(String),
+    Number(&'a str),
+    Bool(bool),
+    Null,
+}
+
+impl Literal<'_> {
+    fn parse(expr: &str) -> Option<Literal<'_>> {
+        let expr = expr.trim();
+        if let Some(text) = expr.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+            // A lone quote inside means several strings, like `'a' || 'b'`.
+            return (!text.replace("''", "").contains('\''))
+                .then(|| Literal::Str(text.replace("''", "'")));
+        }
+        let number = expr.parse::<f64>().is_ok()
+            && expr
+                .chars()
+                .all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+        match expr.to_lowercase().as_str() {
+            _ if number => Some(Literal::Number(expr)),
+            "true" => Some(Literal::Bool(true)),
+            "false" => Some(Literal::Bool(false)),
+            "null" => Some(Literal::Null),
+            _ => None,
+        }
+    }
+
+    fn python(&self) -> String {
+        match self {
+            Literal::Str(text) => json::quote(text),
+            Literal::Number(n) => n.to_string(),
+            Literal::Bool(true) => "True".to_owned(),
+            Literal::Bool(false) => "False".to_owned(),
+            Literal::Null => "None".to_owned(),
+        }
+    }
+
+    fn js(&self) -> String {
+        match self {
+            Literal::Str(text) => json::quote(text),
+            Literal::Number(n) => n.to_string(),
+            Literal::Bool(b) => b.to_string(),
+            Literal::Null => "null".to_owned(),
+        }
+    }
+}
+
+fn sql_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Sample value of a column of type `ty` in the `row`th fixture row, counting
+/// from 1. Keys only depend on the row, so foreign keys can reference the row
+/// of the same number. Custom types get a string, which the database casts to
+/// the column's type.
+fn sample_value(ty: &str, column: &str, row: usize) -> String {
+    let day = (row - 1) % 28 + 1;
+    match Scalar::of(ty) {
+        Some(Scalar::Id | Scalar::Int) => row.to_string(),
+        Some(Scalar::Float) => format!("{row}.5"),
+        Some(Scalar::Bool) => if row % 2 == 1 { "TRUE" } else { "FALSE" }.to_owned(),
+        Some(Scalar::String) | None => sql_string(&format!("{column} {row}")),
+        Some(Scalar::Date) => sql_string(&format!("2024-01-{day:02}")),
+        Some(Scalar::Timestamp) => sql_string(&format!("2024-01-{day:02} 12:00:00")),
+        Some(Scalar::Uuid) => sql_string(&format!("00000000-0000-4000-8000-{row:012}")),
+    }
+}
+
+/// Sample value of `field` in the `row`th fixture row: its example, unless
+/// rows can't share it, or one made up for its type.
+fn sample_field(field: &Field, is_pk: bool, row: usize) -> String {
+    let value = if field.is_enum() {
+        sql_string(&field.variants[(row - 1) % field.variants.len()])
+    } else if let Some(example) = field.example.as_ref().filter(|_| !is_pk && !field.unique) {
+        match Scalar::of(&field.field_type) {
+            Some(Scalar::Id | Scalar::Int | Scalar::Float) if example.parse::<f64>().is_ok() => {
+                example.clone()
+            }
+            _ => sql_string(example),
+        }
+    } else if Scalar::of(&field.field_type).is_none() && field.default.is_some() {
+        // Values of custom types can't be made up, their default is safer.
+        "DEFAULT".to_owned()
+    } else if Scalar::of(&field.field_type).is_none() && field.nullable {
+        "NULL".to_owned()
+    } else {
+        sample_value(&field.field_type, &field.name, row)
+    };
+    if field.list && value != "DEFAULT" && value != "NULL" {
+        format!("ARRAY[{value}]")
+    } else {
+        value
+    }
+}
+
+/// Entities ordered so that, cycles aside, every table comes after the ones
+/// it references.
+fn creation_order<'a>(defs: &'a [Def], def_links: &BTreeMap<&str, Vec<&Link>>) -> Vec<&'a Def> {
+    fn visit<'a>(
+        def: &'a Def,
+        defs: &'a [Def],
+        def_links: &BTreeMap<&str, Vec<&Link>>,
+        seen: &mut HashSet<&'a str>,
+        order: &mut Vec<&'a Def>,
+    ) {
+        if !seen.insert(&def.name) {
+            return;
+        }
+        for link in &def_links[&*def.name] {
+            if let Some(to) = defs.iter().find(|def| def.name == link.to) {
+                visit(to, defs, def_links, seen, order);
+            }
+        }
+        order.push(def);
+    }
+    let mut seen = HashSet::new();
+    let mut order = vec![];
+    for def in defs {
+        visit(def, defs, def_links, &mut seen, &mut order);
+    }
+    order
+}
+
+pub fn emit_sql(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(4);
+    let rows = opts.fixtures.unwrap_or(0);
+    // Foreign keys take the key of the row of the same number.
+    let fk_samples = |link: &Link| -> Vec<String> {
+        let ty = pk_type(defs, &link.to, opts).unwrap_or("id");
+        let column = pk_column(defs, &link.to, opts);
+        (1..=rows)
+            .map(|row| sample_value(ty, &column, row))
+            .collect()
+    };
+    let mut inserts = vec![];
+    let mut sep = "";
+    for def in creation_order(defs, &def_links) {
+        if def.stub {
+            continue;
+        }
+        write!(f, "{sep}")?;
+        sep = "\n";
+        if let Some(reason) = &def.deprecated {
+            writeln!(f, "-- {}", deprecation_text(reason))?;
+        }
+        let table = sql_ident(&opts.entity_name(&def.name));
+        // Columns with an @order go first, the rest keep their place.
+        let mut fields: Vec<(usize, &Field)> = def.fields.iter().enumerate().collect();
+        fields.sort_by_key(|(_, field)| (field.order.is_none(), field.order));
+        let mut lines = vec![];
+        let mut pk = vec![];
+        let mut samples: Vec<(String, Vec<String>)> = vec![];
+        for (i, field) in fields {
+            let mut line = String::new();
+            if let Some(reason) = &field.deprecated {
+                line = format!("{indent}-- {}\n", deprecation_text(reason));
+            }
+            let name = sql_ident(&field.name);
+            line += &format!("{indent}{name} {}", sql_column_type(field));
+            if !field.nullable {
+                line += " NOT NULL";
+            }
+            if field.unique {
+                line += " UNIQUE";
+            }
+            if let Some(default) = &field.default {
+                line += &format!(" DEFAULT {default}");
+            }
+            lines.push(line);
+            let is_pk = def.is_pk(i, opts.first_field_pk, &opts.naming);
+            let key_of = fk_links(&def_links, &def.name)
+                .into_iter()
+                .find(|link| link.fk_name(links, &opts.naming) == field.name);
+            let values = match key_of {
+                Some(link) => fk_samples(link),
+                None => (1..=rows)
+                    .map(|row| sample_field(field, is_pk, row))
+                    .collect(),
+            };
+            samples.push((name.clone(), values));
+            if is_pk {
+                pk.push(name);
+            }
+        }
+        let mut foreign_keys = vec![];
+        for link in fk_links(&def_links, &def.name) {
+            let column = sql_ident(&link.fk_name(links, &opts.naming));
+            let referenced = pk_column(defs, &link.to, opts);
+            let ty = sql_type(pk_type(defs, &link.to, opts).unwrap_or("id"));
+            let null = if fk_nullable(link) { "" } else { " NOT NULL" };
+            let table = sql_ident(&opts.entity_name(&link.to));
+            if link.body.is_pk && !pk.contains(&column) {
+                pk.push(column.clone());
+            }
+            let declared = declares_fk(def, link, links, &opts.naming);
+            if !declared {
+                samples.push((column.clone(), fk_samples(link)));
+            }
+            if let Some(note) = outside_reference(defs, link, &table) {
+                if !declared {
+                    lines.push(format!("{indent}-- {note}\n{indent}{column} {ty}{null}"));
+                }
+                continue;
+            }
+            if !declared {
+                lines.push(format!("{indent}{column} {ty}{null}"));
+            }
+            foreign_keys.push(format!(
+                "{indent}FOREIGN KEY ({column}) REFERENCES {table} ({})",
+                sql_ident(&referenced)
+            ));
+        }
+        if !pk.is_empty() {
+            lines.push(format!("{indent}PRIMARY KEY ({})", pk.join(", ")));
+        }
+        lines.extend(foreign_keys);
+        for check in &def.checks {
+            lines.push(format!("{indent}CHECK ({check})"));
+        }
+        // Enums are text columns only allowed to hold their values.
+        for field in def.fields.iter().filter(|field| field.is_enum()) {
+            let values: Vec<String> = field.variants.iter().map(|v| sql_string(v)).collect();
+            let column = sql_ident(&field.name);
+            let check = if field.list {
+                format!("{column} <@ ARRAY[{}]", values.join(", "))
+            } else {
+                format!("{column} IN ({})", values.join(", "))
+            };
+            lines.push(format!("{indent}CHECK ({check})"));
+        }
+        if lines.is_empty() {
+            writeln!(f, "-- {} has no columns", opts.entity_name(&def.name))?;
+            continue;
+        }
+        writeln!(f, "CREATE TABLE {table} (")?;
+        writeln!(f, "{}", lines.join(",\n"))?;
+        writeln!(f, ");")?;
+        if rows > 0 {
+            let columns: Vec<&str> = samples.iter().map(|(column, _)| column.as_str()).collect();
+            let values: Vec<String> = (0..rows)
+                .map(|row| {
+                    let row: Vec<&str> = samples.iter().map(|(_, values)| &*values[row]).collect();
+                    format!("{indent}({})", row.join(", "))
+                })
+                .collect();
+            inserts.push(format!(
+                "INSERT INTO {table} ({}) VALUES\n{};",
+                columns.join(", "),
+                values.join(",\n")
+            ));
+        }
+    }
+    // Tables are created in the order rows have to be inserted in.
+    for insert in inserts {
+        writeln!(f, "\n{insert}")?;
+    }
+    Ok(())
+}
+
+/// `name` as a DBML identifier, quoted unless it's a bare name.
+fn dbml_ident(name: &str) -> Cow<'_, str> {
+    if is_identifier(name) {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("\"{}\"", name.replace('"', "\\\"")))
+    }
+}
+
+/// Note for a foreign key to a table filtered out of the output. Such a table
+/// can't be referenced, so the column is only marked as pointing outside of it.
+fn outside_reference(defs: &[Def], link: &Link, table: &str) -> Option<String> {
+    defs.iter()
+        .any(|def| def.stub && def.name == link.to)
+        .then(|| format!("References {table}, which is not part of this output"))
+}
+
+/// DBML relationship operator for a link, read from its foreign key to the
+/// key it references.
+fn dbml_ref(link: &Link) -> &'static str {
+    match (link.from_count.is_many(), link.to_count.is_many()) {
+        (true, false) => ">",
+        (false, true) => "<",
+        (false, false) => "-",
+        (true, true) => "<>",
+    }
+}
+
+pub fn emit_dbml(
+    f: &mut dyn Write,
+    Doc { links, defs, .. }: &Doc,
+    opts: &EmitOptions,
+) -> std::io::Result<()> {
+    let def_links = def_links(defs, links);
+    let indent = opts.indent(2);
+    let mut refs = vec![];
+    let mut declared_enums = vec![];
+    let mut sep = "";
+    for def in defs {
+        if def.stub {
+            continue;
+        }
+        write!(f, "{sep}")?;
+        sep = "\n";
+        let table = dbml_ident(&opts.entity_name(&def.name)).into_owned();
+        // Enums are declared on their own, with their name or named after
+        // their table and column.
+        let enum_name = |field: &Field| {
+            let name = if field.is_named_enum() {
+                field.field_type.clone()
+            } else {
+                format!("{}_{}", opts.entity_name(&def.name), field.name)
+            };
+            dbml_ident(&name).into_owned()
+        };
+        for field in def.fields.iter().filter(|field| field.is_enum()) {
+            if declared_enums.contains(&enum_name(field)) {
+                continue;
+            }
+            declared_enums.push(enum_name(field));
+            writeln!(f, "enum {} {{", enum_name(field))?;
+            for variant in &field.variants {
+                writeln!(f, "{indent}{}", dbml_ident(variant))?;
+            }
+            writeln!(f, "}}\n")?;
+        }
+        // Same columns, in the same order, as the SQL tables.
+        let mut fields: Vec<(usize, &Field)> = def.fields.iter().enumerate().collect();
+        fields.sort_by_key(|(_, field)| (field.order.is_none(), field.order));
+        let mut columns = vec![];
+        for (i, field) in fields {
+            let name = dbml_ident(&field.name).into_owned();
+            let is_pk = def.is_pk(i, opts.first_field_pk, &opts.naming);
+            let mut settings = vec![];
+            if field.nullable {
+                settings.push("null".to_owned());
+            }
+            if field.unique {
+                settings.push("unique".to_owned());
+            }
+            if let Some(default) = &field.default {
+                settings.push(format!("default: `{default}`"));
+            }
+            let ty = match (field.is_enum(), field.list) {
+                (true, false) => enum_name(field),
+                (true, true) => format!("{}[]", enum_name(field)),
+                (false, _) => sql_column_type(field),
+            };
+            columns.push((name, ty, is_pk, settings, None));
+        }
+        for link in fk_links(&def_links, &def.name) {
+            let column = dbml_ident(&link.fk_name(links, &opts.naming)).into_owned();
+            let ty = sql_type(pk_type(defs, &link.to, opts).unwrap_or("id"));
+            let to = dbml_ident(&opts.entity_name(&link.to)).into_owned();
+            let note = outside_reference(defs, link, &to);
+            if note.is_none() {
+                let referenced = pk_column(defs, &link.to, opts);
+                refs.push(format!(
+                    "Ref: {table}.{column} {} {to}.{}",
+                    dbml_ref(link),
+                    dbml_ident(&referenced)
+                ));
+            }
+            if declares_fk(def, link, links, &opts.naming) {
+                if let Some(declared) = columns.iter_mut().find(|(name, ..)| *name == column) {
+                    declared.2 |= link.body.is_pk;
+                }
+                continue;
+            }
+            let settings = if fk_nullable(link) {
+                vec!["null".to_owned()]
+            } else {
+                vec![]
+            };
+            columns.push((column, ty.to_owned(), link.body.is_pk, settings, note));
+        }
+        if columns.is_empty() {
+            writeln!(f, "// {table} has no columns")?;
+            continue;
+        }
+        writeln!(f, "Table {table} {{")?;
+        // A single key column is marked inline, composite keys need an index.
+        let pk: Vec<&str> = columns
+            .iter()
+            .filter(|(_, _, is_pk, ..)| *is_pk)
+            .map(|(name, ..)| name.as_str())
+            .collect();
+        for (name, ty, is_pk, settings, note) in &columns {
+            if let Some(note) = note {
+                writeln!(f, "{indent}// {note}")?;
+            }
+            let mut settings = settings.clone();
+            if *is_pk && pk.len() == 1 {
+                settings.insert(0, "pk".to_owned());
+            }
+            if settings.is_empty() {
+                writeln!(f, "{indent}{name} {ty}")?;
+            } else {
+                writeln!(f, "{indent}{name} {ty} [{}]", settings.join(", "))?;
+            }
+        }
+        if pk.len() > 1 {
+            writeln!(f, "\n{indent}indexes {{")?;
+            writeln!(f, "{indent}{indent}({}) [pk]", pk.join(", "))?;
+            writeln!(f, "{indent}}}")?;
+        }
+        writeln!(f, "}}")?;
+    }
+    if !refs.is_empty() {
+        writeln!(f)?;
+        for line in refs {
+            writeln!(f, "{line}")?;
+        }
+    }
+    Ok(())
+}
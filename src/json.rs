@@ -0,0 +1,90 @@
+use std::io::Write;
+
+/// A JSON value, just enough to pretty-print what the emitters produce.
+#[derive(Debug, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn str(s: impl Into<String>) -> Self {
+        Json::Str(s.into())
+    }
+
+    pub fn obj<K: Into<String>>(entries: impl IntoIterator<Item = (K, Json)>) -> Self {
+        Json::Obj(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Writes the value followed by a newline.
+    pub fn write(&self, f: &mut dyn Write) -> std::io::Result<()> {
+        self.write_indented(f, 0)?;
+        writeln!(f)
+    }
+
+    fn write_indented(&self, f: &mut dyn Write, depth: usize) -> std::io::Result<()> {
+        let pad = "  ".repeat(depth + 1);
+        let end = "  ".repeat(depth);
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Int(n) => write!(f, "{n}"),
+            Json::Str(s) => write_str(f, s),
+            Json::Arr(items) if items.is_empty() => write!(f, "[]"),
+            Json::Obj(entries) if entries.is_empty() => write!(f, "{{}}"),
+            Json::Arr(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{pad}")?;
+                    item.write_indented(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{end}]")
+            }
+            Json::Obj(entries) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write!(f, "{pad}")?;
+                    write_str(f, key)?;
+                    write!(f, ": ")?;
+                    value.write_indented(f, depth + 1)?;
+                    writeln!(f, "{}", if i + 1 < entries.len() { "," } else { "" })?;
+                }
+                write!(f, "{end}}}")
+            }
+        }
+    }
+}
+
+impl From<Option<&str>> for Json {
+    fn from(s: Option<&str>) -> Self {
+        s.map(Json::str).unwrap_or(Json::Null)
+    }
+}
+
+/// `s` as a JSON string literal, which JavaScript and Python read the same.
+pub fn quote(s: &str) -> String {
+    let mut out = vec![];
+    write_str(&mut out, s).expect("writing to a Vec can't fail");
+    String::from_utf8(out).expect("escaping keeps the text UTF-8")
+}
+
+fn write_str(f: &mut dyn Write, s: &str) -> std::io::Result<()> {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
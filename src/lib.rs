@@ -0,0 +1,1551 @@
+//! Parser and emitters for DiaLang, a small language describing entities and
+//! the relationships between them.
+//!
+//! [`parse_str`] turns source text into a [`Doc`], which any of the functions
+//! in [`emitters`] can write out.
+
+use std::{
+    borrow::Cow,
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+};
+
+use anyhow::{anyhow, bail};
+use heck::{ToLowerCamelCase, ToPascalCase, ToSnakeCase};
+use pest::{
+    Parser,
+    error::LineColLocation,
+    iterators::{Pair, Pairs},
+};
+
+pub mod emitters;
+mod json;
+pub mod types;
+
+use crate::emitters::Notation;
+
+#[derive(pest_derive::Parser)]
+#[grammar = "rules.pest"]
+struct MyParser;
+
+macro_rules! ensure_rule {
+    ($tk:ident, $rule:expr) => {
+        if $tk.as_rule() != $rule {
+            return Err(Error::at(
+                &$tk,
+                format!("Expected {:?}, got {:?}", $rule, $tk.as_rule()),
+            ));
+        }
+    };
+}
+
+#[derive(Debug)]
+struct Error {
+    cause: String,
+    /// Where in the source the error is, if known.
+    line_col: Option<(usize, usize)>,
+}
+
+impl Error {
+    fn new(cause: impl Into<String>) -> Error {
+        Error {
+            cause: cause.into(),
+            line_col: None,
+        }
+    }
+
+    fn at(tk: &Token, cause: impl Into<String>) -> Error {
+        Error {
+            cause: cause.into(),
+            line_col: Some(tk.line_col()),
+        }
+    }
+
+    /// Points the error at `line_col` unless it already says where it is.
+    fn or_at(self, line_col: (usize, usize)) -> Error {
+        Error {
+            line_col: self.line_col.or(Some(line_col)),
+            ..self
+        }
+    }
+
+    /// The error followed by the line of `source` it points at, like pest
+    /// shows syntax errors.
+    fn with_source(self, source: &str) -> anyhow::Error {
+        let Some((line, col)) = self.line_col else {
+            return anyhow!(self);
+        };
+        let text = source.lines().nth(line - 1).unwrap_or_default();
+        // Tabs before the caret are kept so it lines up all the same.
+        let pad: String = text
+            .chars()
+            .take(col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(line.to_string().len());
+        anyhow!(
+            "{}\n{gutter}--> {line}:{col}\n{gutter} |\n{line} | {text}\n{gutter} | {pad}^",
+            self.cause
+        )
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.cause)?;
+        if let Some((line, col)) = self.line_col {
+            write!(f, " at line {line}, column {col}")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for Error {}
+
+type ParseResult<T> = Result<T, Error>;
+
+trait Parse: Sized {
+    fn parse(tk: Token) -> ParseResult<Self>;
+
+    /// Like [`Parse::parse`], pointing errors that don't say where they are
+    /// at `tk`.
+    fn parse_at(tk: Token) -> ParseResult<Self> {
+        let line_col = tk.line_col();
+        Self::parse(tk).map_err(|err| err.or_at(line_col))
+    }
+}
+#[easy_ext::ext]
+impl Pairs<'_, Rule> {
+    fn next_item<T: Parse>(&mut self) -> ParseResult<T> {
+        T::parse_at(self.next().ok_or_else(|| Error::new("Missing"))?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::IntoStaticStr, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LinkN {
+    One,
+    MaybeOne,
+    Many,
+    MaybeMany,
+}
+
+impl Display for LinkN {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Notation::MinMax.symbol(*self))
+    }
+}
+
+impl LinkN {
+    pub const ALL: [LinkN; 4] = [LinkN::One, LinkN::MaybeOne, LinkN::Many, LinkN::MaybeMany];
+
+    /// How many entities this count allows, in words.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            LinkN::One => "exactly one",
+            LinkN::MaybeOne => "at most one",
+            LinkN::Many => "one or more",
+            LinkN::MaybeMany => "zero or more",
+        }
+    }
+
+    pub fn is_many(&self) -> bool {
+        matches!(self, LinkN::Many | LinkN::MaybeMany)
+    }
+
+    pub fn is_optional(&self) -> bool {
+        matches!(self, LinkN::MaybeOne | LinkN::MaybeMany)
+    }
+}
+
+impl LinkN {
+    /// How the count is written in DiaLang source.
+    pub fn source(&self) -> &'static str {
+        match self {
+            LinkN::One => "1",
+            LinkN::MaybeOne => "1?",
+            LinkN::Many => "n",
+            LinkN::MaybeMany => "n?",
+        }
+    }
+}
+
+impl Parse for LinkN {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::link_n);
+        Ok(match tk.as_str().trim() {
+            "1" => Self::One,
+            "1?" => Self::MaybeOne,
+            "n" => Self::Many,
+            "n?" => Self::MaybeMany,
+            other => return Err(Error::at(&tk, format!("Unknown cardinality `{other}`"))),
+        })
+    }
+}
+
+impl Parse for String {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        if tk.as_rule() == Rule::string {
+            let line_col = tk.line_col();
+            let name = literal(tk)?;
+            if name.trim().is_empty() {
+                return Err(Error::new("Quoted names can't be blank").or_at(line_col));
+            }
+            return Ok(name);
+        }
+        ensure_rule!(tk, Rule::name);
+        Ok(tk.as_str().to_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LinkBody {
+    pub is_pk: bool,
+    /// Written `<->`: neither side holds a foreign key to the other.
+    pub symmetric: bool,
+}
+
+impl Parse for LinkBody {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::ARROW_BODY);
+        let is_pk = tk.as_str().contains('=');
+        let symmetric = tk.as_str().starts_with('<');
+        Ok(Self { is_pk, symmetric })
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Link {
+    pub from: String,
+    pub from_count: LinkN,
+    pub body: LinkBody,
+    pub to_count: LinkN,
+    pub to: String,
+    pub label: Option<String>,
+    /// Layout hint from `@priority`.
+    pub priority: Option<Priority>,
+    /// Line drawn for the link, from `@style`.
+    pub style: Option<LineStyle>,
+    /// Color of the line, from `@color`: a Graphviz color name or `#rrggbb`.
+    pub color: Option<String>,
+    /// Attributes of the relationship itself, turning it into an association
+    /// entity.
+    pub fields: Vec<Field>,
+    /// Stands for a junction entity hidden by `--collapse-junctions`.
+    pub junction: bool,
+}
+
+/// How strongly the layout should pull a link straight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::IntoStaticStr, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Priority {
+    High,
+    Low,
+}
+
+/// How the line of a link is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::IntoStaticStr, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LineStyle {
+    Solid,
+    Dashed,
+    Dotted,
+    Bold,
+}
+
+/// Whether `color` can go in a diagram as-is: a color name or a hex code.
+fn is_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !color.is_empty() && color.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
+impl Parse for Link {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::link);
+        let mut tk = tk.into_inner();
+        let from: String = tk.next_item()?;
+        let from_count: LinkN = tk.next_item()?;
+        let body: LinkBody = tk.next_item()?;
+        let to_count: LinkN = tk.next_item()?;
+        let to: String = tk.next_item()?;
+        let mut label = None;
+        let mut priority = None;
+        let mut style = None;
+        let mut color = None;
+        let mut fields = vec![];
+        let mut positions = vec![];
+        for tk in tk {
+            match tk.as_rule() {
+                Rule::name | Rule::string => label = Some(String::parse_at(tk)?),
+                Rule::annotation => {
+                    let annotation = Annotation::parse_at(tk)?;
+                    match annotation.name.as_str() {
+                        "priority" => {
+                            let value = annotation.value()?;
+                            priority = Some(value.parse().map_err(|_| {
+                                annotation.error(format!(
+                                    "@priority must be `high` or `low`, got `{value}`"
+                                ))
+                            })?);
+                        }
+                        "style" => {
+                            let value = annotation.value()?;
+                            style = Some(value.parse().map_err(|_| {
+                                annotation.error(format!(
+                                    "@style must be `solid`, `dashed`, `dotted` or `bold`, got `{value}`"
+                                ))
+                            })?);
+                        }
+                        "color" => {
+                            let value = annotation.value()?;
+                            if !is_color(&value) {
+                                return Err(annotation.error(format!(
+                                    "@color must be a color name or `#rrggbb`, got `{value}`"
+                                )));
+                            }
+                            color = Some(value);
+                        }
+                        _ => return Err(annotation.unknown()),
+                    }
+                }
+                _ => {
+                    positions.push(tk.line_col());
+                    fields.push(Field::parse_at(tk)?);
+                }
+            }
+        }
+        let owner = label.as_deref().unwrap_or(&from);
+        check_field_names(owner, &fields, &positions)?;
+        check_orders(owner, &fields)?;
+        Ok(Self {
+            from,
+            from_count,
+            body,
+            to_count,
+            to,
+            label,
+            priority,
+            style,
+            color,
+            fields,
+            junction: false,
+        })
+    }
+}
+
+impl Link {
+    /// Whether `other` declares the same relationship, whatever its fields
+    /// and layout hints.
+    pub fn same_as(&self, other: &Link) -> bool {
+        self.from == other.from
+            && self.from_count == other.from_count
+            && self.body == other.body
+            && self.to_count == other.to_count
+            && self.to == other.to
+            && self.label == other.label
+    }
+
+    /// Whether this link adds a foreign key column to `from`. Symmetric links
+    /// have no side to hold it and collapsed junctions stand for a table.
+    pub fn has_fk(&self) -> bool {
+        !self.junction && !self.body.symmetric
+    }
+
+    /// Name of the foreign key column this link adds to `from`, given all the
+    /// `links` of the document.
+    pub fn fk_name(&self, links: &[Link], naming: &Naming) -> String {
+        naming.fk_name(self.fk_target(links))
+    }
+
+    /// What the foreign key is named after: the referenced entity, or the
+    /// label of a link to the entity itself or to an entity `from` has other
+    /// foreign keys to, so that those get distinct columns.
+    pub fn fk_stem(&self, links: &[Link]) -> String {
+        identifier(&self.fk_target(links).to_lowercase()).into()
+    }
+
+    fn fk_target(&self, links: &[Link]) -> &str {
+        let shared = || {
+            links
+                .iter()
+                .filter(|other| other.has_fk() && other.from == self.from && other.to == self.to)
+                .count()
+                > 1
+        };
+        match &self.label {
+            Some(label) if self.from == self.to || shared() => label,
+            _ => &self.to,
+        }
+    }
+}
+
+/// How key columns are named. The default names primary keys `id` and
+/// foreign keys like `clientaddress_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    /// Name of the field that is an entity's primary key, with `{entity}`
+    /// standing for the entity's name.
+    pub pk_name: String,
+    /// Appended to the referenced entity to name a foreign key.
+    pub fk_suffix: String,
+    /// How the referenced entity is written in foreign key names.
+    pub fk_case: FkCase,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Naming {
+            pk_name: "id".to_owned(),
+            fk_suffix: "_id".to_owned(),
+            fk_case: FkCase::default(),
+        }
+    }
+}
+
+impl Naming {
+    /// Name of the primary key field of the entity called `entity`.
+    pub fn pk_name(&self, entity: &str) -> String {
+        self.pk_name.replace("{entity}", entity)
+    }
+
+    /// Name of a foreign key to `to`, an entity or a label.
+    pub fn fk_name(&self, to: &str) -> String {
+        let to = match self.fk_case {
+            FkCase::Lower => to.to_lowercase(),
+            FkCase::Snake => to.to_snake_case(),
+            FkCase::Pascal => to.to_pascal_case(),
+            FkCase::Camel => to.to_lower_camel_case(),
+        };
+        format!("{}{}", identifier(&to), self.fk_suffix)
+    }
+}
+
+/// How an entity shows what it inherits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum InheritanceStyle {
+    /// The inherited fields are copied into the entity.
+    Flatten,
+    /// The entity keeps its own fields and refers to its parents for the
+    /// rest.
+    Reference,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, strum::EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum FkCase {
+    /// `clientaddress`
+    #[default]
+    Lower,
+    /// `client_address`
+    Snake,
+    /// `ClientAddress`
+    Pascal,
+    /// `clientAddress`
+    Camel,
+}
+
+/// `name` with each run of anything but letters, digits and `_` turned into
+/// a single `_`, for quoted names used where only identifiers fit.
+pub fn identifier(name: &str) -> Cow<'_, str> {
+    if is_identifier(name) {
+        return Cow::Borrowed(name);
+    }
+    let words: Vec<&str> = name
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    Cow::Owned(words.join("_"))
+}
+
+/// Whether `name` could have been written without quotes.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Field {
+    /// Type of the field, or of its items if it is a list. `enum` for enums.
+    pub field_type: String,
+    /// Values of an `enum(...)` type, empty for other types.
+    pub variants: Vec<String>,
+    /// Whether the field is a list of `field_type`, written `type[]`.
+    pub list: bool,
+    pub name: String,
+    /// Sample value for documentation, from an `example` clause.
+    pub example: Option<String>,
+    /// Physical column position from `@order`, for emitters that create
+    /// tables.
+    pub order: Option<u32>,
+    /// Reason from `@deprecated`, empty if none was given.
+    pub deprecated: Option<String>,
+    /// Whether the field is marked `@pk`.
+    pub pk: bool,
+    /// Whether the field is written with a `?`, allowing null.
+    pub nullable: bool,
+    /// Whether the field is marked `@unique`.
+    pub unique: bool,
+    /// SQL expression from `@default`, copied as is.
+    pub default: Option<String>,
+    /// What the field holds, from `@doc`, shown as a tooltip in diagrams.
+    pub doc: Option<String>,
+}
+
+impl Parse for Field {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::field);
+        let mut field = tk.into_inner();
+        let (field_type, variants) = match field.peek() {
+            Some(tk) if tk.as_rule() == Rule::enum_type => {
+                field.next();
+                let variants: ParseResult<Vec<String>> =
+                    tk.into_inner().map(String::parse_at).collect();
+                ("enum".to_owned(), variants?)
+            }
+            _ => (field.next_item()?, vec![]),
+        };
+        let list = field.peek().is_some_and(|tk| tk.as_rule() == Rule::list);
+        if list {
+            field.next();
+        }
+        let name = field.next_item()?;
+        let mut example = None;
+        let mut order = None;
+        let mut deprecated = None;
+        let mut pk = false;
+        let mut nullable = false;
+        let mut unique = false;
+        let mut default = None;
+        let mut doc = None;
+        for tk in field {
+            if tk.as_rule() == Rule::nullable {
+                nullable = true;
+                continue;
+            }
+            if tk.as_rule() == Rule::example {
+                example = Some(literal(tk.into_inner().next().unwrap())?);
+                continue;
+            }
+            let annotation = Annotation::parse_at(tk)?;
+            match annotation.name.as_str() {
+                "order" => {
+                    let value = annotation.value()?;
+                    order = Some(value.parse().map_err(|_| {
+                        annotation.error(format!("@order must be a number, got `{value}`"))
+                    })?);
+                }
+                "deprecated" => deprecated = Some(annotation.value.unwrap_or_default()),
+                "pk" => pk = true,
+                "unique" => unique = true,
+                "default" => default = Some(annotation.value()?),
+                "doc" => doc = Some(annotation.value()?),
+                _ => return Err(annotation.unknown()),
+            }
+        }
+        Ok(Field {
+            field_type,
+            variants,
+            list,
+            name,
+            example,
+            order,
+            deprecated,
+            pk,
+            nullable,
+            unique,
+            default,
+            doc,
+        })
+    }
+}
+
+impl Field {
+    /// Whether the field is an `enum(...)` or typed after a named enum.
+    pub fn is_enum(&self) -> bool {
+        !self.variants.is_empty()
+    }
+
+    /// Whether the field is typed after an `enum Name { ... }` declaration.
+    pub fn is_named_enum(&self) -> bool {
+        self.is_enum() && self.field_type != "enum"
+    }
+
+    /// Type as written in DiaLang, with `[]` for lists.
+    pub fn type_name(&self) -> Cow<'_, str> {
+        if self.list {
+            Cow::Owned(format!("{}[]", self.field_type))
+        } else {
+            Cow::Borrowed(&self.field_type)
+        }
+    }
+}
+
+/// Fails if two of `fields` share a name, which would make them the same
+/// column. `positions` holds the source line and column of each field.
+fn check_field_names(
+    owner: &str,
+    fields: &[Field],
+    positions: &[(usize, usize)],
+) -> ParseResult<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if let Some(j) = fields[..i]
+            .iter()
+            .position(|other| other.name == field.name)
+        {
+            let cause = format!(
+                "Field `{}` of `{owner}` is declared twice, at lines {} and {}",
+                field.name, positions[j].0, positions[i].0
+            );
+            return Err(Error::new(cause).or_at(positions[i]));
+        }
+    }
+    Ok(())
+}
+
+/// Fails if two of `fields` ask for the same `@order`.
+fn check_orders(owner: &str, fields: &[Field]) -> ParseResult<()> {
+    for (i, field) in fields.iter().enumerate() {
+        let Some(order) = field.order else {
+            continue;
+        };
+        if let Some(other) = fields[..i].iter().find(|other| other.order == Some(order)) {
+            return Err(Error::new(format!(
+                "Fields `{}` and `{}` of `{owner}` both have @order {order}",
+                other.name, field.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Text of a string or number literal, with quotes and escapes resolved.
+fn literal(tk: Token) -> ParseResult<String> {
+    match tk.as_rule() {
+        Rule::string => Ok(unescape(tk.into_inner().as_str())),
+        Rule::number => Ok(tk.as_str().to_owned()),
+        rule => Err(Error::at(&tk, format!("Expected a literal, got {rule:?}"))),
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+/// `@name value` attached to a definition.
+#[derive(Debug, Clone)]
+struct Annotation {
+    name: String,
+    value: Option<String>,
+    line_col: (usize, usize),
+}
+
+impl Parse for Annotation {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::annotation);
+        let line_col = tk.line_col();
+        let mut tk = tk.into_inner();
+        Ok(Annotation {
+            name: tk.next_item()?,
+            value: match tk.next() {
+                Some(value) if value.as_rule() == Rule::string => Some(literal(value)?),
+                Some(value) => Some(String::parse_at(value)?),
+                None => None,
+            },
+            line_col,
+        })
+    }
+}
+
+impl Annotation {
+    fn value(&self) -> ParseResult<String> {
+        self.value
+            .clone()
+            .ok_or_else(|| self.error(format!("@{} needs a value", self.name)))
+    }
+
+    fn unknown(&self) -> Error {
+        self.error(format!("Unknown annotation @{}", self.name))
+    }
+
+    /// An error pointing at the annotation.
+    fn error(&self, cause: String) -> Error {
+        Error::new(cause).or_at(self.line_col)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Def {
+    pub name: String,
+    pub fields: Vec<Field>,
+    /// Free-form tag from `@kind`, used to filter what gets emitted.
+    pub kind: Option<String>,
+    /// Graphviz shape from `@shape`, drawn instead of the plain table.
+    pub shape: Option<String>,
+    /// Predicates of `check (...)` clauses, without the outer parentheses.
+    pub checks: Vec<String>,
+    /// Reason from `@deprecated`, empty if none was given.
+    pub deprecated: Option<String>,
+    /// Placeholder for an entity that lives outside of the rendered subset.
+    pub stub: bool,
+}
+
+type Token<'a> = Pair<'a, Rule>;
+
+impl Parse for Def {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::def);
+        let mut def = tk.into_inner();
+        let name: String = def.next_item()?;
+        let mut kind = None;
+        let mut shape = None;
+        let mut deprecated = None;
+        let mut fields = vec![];
+        let mut positions = vec![];
+        let mut checks = vec![];
+        for tk in def {
+            if tk.as_rule() == Rule::check {
+                let line_col = tk.line_col();
+                let predicate = check_predicate(tk.into_inner().as_str());
+                checks.push(predicate.map_err(|err| err.or_at(line_col))?);
+                continue;
+            }
+            if tk.as_rule() != Rule::annotation {
+                positions.push(tk.line_col());
+                fields.push(Field::parse_at(tk)?);
+                continue;
+            }
+            let annotation = Annotation::parse_at(tk)?;
+            match annotation.name.as_str() {
+                "kind" => kind = Some(annotation.value()?),
+                "shape" => shape = Some(annotation.value()?),
+                "deprecated" => deprecated = Some(annotation.value.unwrap_or_default()),
+                _ => return Err(annotation.unknown()),
+            }
+        }
+        check_field_names(&name, &fields, &positions)?;
+        check_orders(&name, &fields)?;
+        let def = Def {
+            name,
+            fields,
+            kind,
+            shape,
+            checks,
+            deprecated,
+            stub: false,
+        };
+        Ok(def)
+    }
+}
+
+/// Strips the parentheses around a `check` predicate, making sure they and
+/// any brackets inside are balanced. Quoted text is left alone.
+fn check_predicate(text: &str) -> ParseResult<String> {
+    let text = text.trim_end();
+    let unbalanced = || Error::new(format!("Unbalanced brackets in check {text}"));
+    let mut open = vec![];
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(' | '[' | '{') => open.push(c),
+            (None, ')' | ']' | '}') => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if open.pop() != Some(expected) {
+                    return Err(unbalanced());
+                }
+                if open.is_empty() && i + 1 != text.len() {
+                    return Err(Error::new(format!(
+                        "Unexpected text after check {}",
+                        &text[..=i]
+                    )));
+                }
+            }
+            (None, _) => {}
+        }
+    }
+    if !open.is_empty() || quote.is_some() {
+        return Err(unbalanced());
+    }
+    Ok(text[1..text.len() - 1].trim().to_owned())
+}
+
+impl Def {
+    /// Whether the field at `index` is part of this entity's primary key.
+    ///
+    /// Fields marked `@pk` make up the key when there are any. Otherwise,
+    /// with `first_field_pk` the first field is the key, and without it a
+    /// field named after [`Naming::pk_name`] is.
+    pub fn is_pk(&self, index: usize, first_field_pk: bool, naming: &Naming) -> bool {
+        if self.fields.iter().any(|field| field.pk) {
+            self.fields[index].pk
+        } else if first_field_pk {
+            index == 0
+        } else {
+            self.fields[index].name == naming.pk_name(&self.name)
+        }
+    }
+
+    pub fn has_pk(&self, first_field_pk: bool, naming: &Naming) -> bool {
+        (0..self.fields.len()).any(|i| self.is_pk(i, first_field_pk, naming))
+    }
+}
+
+/// Version of the DiaLang grammar a document is written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+/// Latest grammar version understood by this build.
+pub const GRAMMAR_VERSION: Version = Version { major: 1, minor: 0 };
+
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl Version {
+    /// Whether this build understands documents targeting this version.
+    pub fn is_supported(&self) -> bool {
+        self.major == GRAMMAR_VERSION.major && *self <= GRAMMAR_VERSION
+    }
+}
+
+impl Parse for Version {
+    fn parse(tk: Token) -> ParseResult<Self> {
+        ensure_rule!(tk, Rule::directive);
+        let line_col = tk.line_col();
+        let version = tk.into_inner().as_str();
+        let (major, minor) = version.split_once('.').unwrap();
+        let number = |n: &str| {
+            n.parse()
+                .map_err(|_| Error::new(format!("Invalid version {version}")).or_at(line_col))
+        };
+        Ok(Version {
+            major: number(major)?,
+            minor: number(minor)?,
+        })
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Doc {
+    pub links: Vec<Link>,
+    pub defs: Vec<Def>,
+    /// Entities to draw together, from `group` blocks.
+    pub groups: Vec<Group>,
+    /// From the `dialang X.Y` directive. Documents without one target
+    /// [`GRAMMAR_VERSION`].
+    pub version: Option<Version>,
+    /// Paths from `include` statements, as written. Reading and merging the
+    /// files is left to the caller.
+    pub includes: Vec<String>,
+    /// Entities that are a kind of another, from `Child :> Parent`.
+    pub inherits: Vec<Inherit>,
+    /// Named enums, from `enum Name { ... }`.
+    pub enums: Vec<Enum>,
+}
+
+/// An `enum Name { a, b }` declaration. Fields typed `Name` take one of its
+/// values, as if they were written `enum(a, b)`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+/// A `Child :> Parent` statement: every `child` is also a `parent`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Inherit {
+    pub child: String,
+    pub parent: String,
+}
+
+/// A `group "Name" { ... }` block. Its entities are part of [`Doc::defs`]
+/// like any other.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Group {
+    pub name: String,
+    /// Names of the entities declared in the block.
+    pub defs: Vec<String>,
+}
+
+/// Entities a junction made out of `link` has a foreign key to, each with the
+/// label of its link. Junctions of a link from an entity to itself name their
+/// second key after the link's label, so the two don't share a column.
+fn junction_sides(link: &Link) -> [(&String, Option<String>); 2] {
+    let second = (link.from == link.to).then(|| {
+        link.label
+            .clone()
+            .unwrap_or_else(|| format!("other_{}", link.to))
+    });
+    [(&link.from, None), (&link.to, second)]
+}
+
+impl Doc {
+    /// Warning for a `dialang X.Y` directive this build doesn't support, which
+    /// is left for the caller to show.
+    pub fn version_warning(&self) -> Option<String> {
+        let version = self.version.filter(|version| !version.is_supported())?;
+        Some(format!(
+            "targets dialang {version}, this build supports {}.0 to {GRAMMAR_VERSION}",
+            GRAMMAR_VERSION.major
+        ))
+    }
+
+    /// The group `def` was declared in, if any.
+    pub fn group_of(&self, def: &str) -> Option<&Group> {
+        self.groups
+            .iter()
+            .find(|group| group.defs.iter().any(|name| name == def))
+    }
+
+    /// Gives fields typed after a named enum the values of that enum. Enums
+    /// may be declared in another file, so this runs again once they are
+    /// merged.
+    pub fn resolve_enums(&mut self) {
+        let fields = self.defs.iter_mut().flat_map(|def| &mut def.fields);
+        let link_fields = self.links.iter_mut().flat_map(|link| &mut link.fields);
+        for field in fields.chain(link_fields) {
+            if let Some(decl) = self.enums.iter().find(|decl| decl.name == field.field_type) {
+                field.variants = decl.variants.clone();
+            }
+        }
+    }
+
+    pub fn def(&self, name: &str) -> Option<&Def> {
+        self.defs.iter().find(|def| def.name == name)
+    }
+
+    /// Replaces every link carrying fields by an association entity holding
+    /// those fields plus a foreign key to each side of the link. With a `==`
+    /// link the two foreign keys form the association's primary key.
+    pub fn expand_associations(mut self, naming: &Naming) -> anyhow::Result<Doc> {
+        let mut links = vec![];
+        for link in std::mem::take(&mut self.links) {
+            if link.fields.is_empty() {
+                links.push(link);
+                continue;
+            }
+            let name = link
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("{}{}", link.from, link.to));
+            for (side, label) in junction_sides(&link) {
+                let fk = naming.fk_name(label.as_deref().unwrap_or(side));
+                if link.fields.iter().any(|field| field.name == fk) {
+                    bail!("Field `{fk}` of association `{name}` collides with its foreign key");
+                }
+                links.push(Link {
+                    from: name.clone(),
+                    from_count: LinkN::Many,
+                    body: link.body.clone(),
+                    to_count: LinkN::One,
+                    to: side.clone(),
+                    label,
+                    priority: link.priority,
+                    style: link.style,
+                    color: link.color.clone(),
+                    fields: vec![],
+                    junction: false,
+                });
+            }
+            self.defs.push(Def {
+                name,
+                fields: link.fields,
+                ..Def::default()
+            });
+        }
+        self.links = links;
+        Ok(self)
+    }
+
+    /// Parents of the entity called `name`, then theirs and so on, nearest
+    /// first and each one once.
+    pub fn ancestors(&self, name: &str) -> Vec<&str> {
+        let mut ancestors: Vec<&str> = vec![];
+        let mut next = vec![name];
+        while !next.is_empty() {
+            let mut parents = vec![];
+            for inherit in &self.inherits {
+                if next.contains(&inherit.child.as_str())
+                    && inherit.parent != name
+                    && !ancestors.contains(&inherit.parent.as_str())
+                {
+                    ancestors.push(&inherit.parent);
+                    parents.push(inherit.parent.as_str());
+                }
+            }
+            next = parents;
+        }
+        ancestors
+    }
+
+    /// Copies the fields of each entity's ancestors into it, for tables,
+    /// which can't inherit. Inherited fields come first, from the farthest
+    /// ancestor, and a field the entity declares itself wins over inherited
+    /// ones of the same name.
+    pub fn inline_inherited(mut self) -> Doc {
+        let inlined: Vec<Vec<Field>> = self
+            .defs
+            .iter()
+            .map(|def| {
+                let mut fields: Vec<Field> = vec![];
+                for ancestor in self.ancestors(&def.name).into_iter().rev() {
+                    let Some(ancestor) = self.def(ancestor) else {
+                        continue;
+                    };
+                    for field in &ancestor.fields {
+                        let declared = |other: &Field| other.name == field.name;
+                        if !def.fields.iter().any(declared) && !fields.iter().any(declared) {
+                            fields.push(field.clone());
+                        }
+                    }
+                }
+                fields.extend(def.fields.iter().cloned());
+                fields
+            })
+            .collect();
+        for (def, fields) in self.defs.iter_mut().zip(inlined) {
+            def.fields = fields;
+        }
+        self
+    }
+
+    /// Links each entity to its parents with a `1?==1` link, for tables that
+    /// keep their own fields: a row shares its key with the row of each
+    /// parent holding the inherited fields.
+    pub fn reference_inherited(mut self) -> Doc {
+        for inherit in &self.inherits {
+            self.links.push(Link {
+                from: inherit.child.clone(),
+                from_count: LinkN::MaybeOne,
+                body: LinkBody {
+                    is_pk: true,
+                    symmetric: false,
+                },
+                to_count: LinkN::One,
+                to: inherit.parent.clone(),
+                label: None,
+                priority: None,
+                style: None,
+                color: None,
+                fields: vec![],
+                junction: false,
+            });
+        }
+        self
+    }
+
+    /// Replaces every many-to-many link by a junction entity whose primary
+    /// key is made of a foreign key to each side, as a relational schema
+    /// needs. Links carrying fields are expected to be expanded already.
+    pub fn expand_many_to_many(mut self) -> anyhow::Result<Doc> {
+        let mut links = vec![];
+        for link in std::mem::take(&mut self.links) {
+            // Symmetric links have no foreign key to begin with.
+            if !(link.from_count.is_many() && link.to_count.is_many())
+                || link.junction
+                || link.body.symmetric
+            {
+                links.push(link);
+                continue;
+            }
+            let name = link
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("{}_{}", link.from, link.to));
+            if self.def(&name).is_some() {
+                bail!(
+                    "Junction table `{name}` linking `{}` and `{}` collides with an entity of the same name",
+                    link.from,
+                    link.to
+                );
+            }
+            for (side, label) in junction_sides(&link) {
+                links.push(Link {
+                    from: name.clone(),
+                    from_count: LinkN::Many,
+                    body: LinkBody {
+                        is_pk: true,
+                        symmetric: false,
+                    },
+                    to_count: LinkN::One,
+                    to: side.clone(),
+                    label,
+                    priority: link.priority,
+                    style: link.style,
+                    color: link.color.clone(),
+                    fields: vec![],
+                    junction: false,
+                });
+            }
+            self.defs.push(Def {
+                name,
+                ..Def::default()
+            });
+        }
+        self.links = links;
+        Ok(self)
+    }
+
+    /// Undoes `expand_associations` for drawing: every junction entity, one
+    /// whose only key is made of `==` links to two entities and that nothing
+    /// links to, is replaced by a many-to-many link between those two,
+    /// labeled with its name.
+    pub fn collapse_junctions(mut self, first_field_pk: bool, naming: &Naming) -> Doc {
+        let junctions: Vec<(String, [usize; 2])> = self
+            .defs
+            .iter()
+            .filter(|def| !def.stub && !def.has_pk(first_field_pk, naming))
+            .filter(|def| !self.links.iter().any(|link| link.to == def.name))
+            .filter_map(|def| {
+                let sides: Vec<usize> = (0..self.links.len())
+                    .filter(|&i| self.links[i].from == def.name)
+                    .collect();
+                let fks =
+                    |&i: &usize| self.links[i].body.is_pk && self.links[i].to_count == LinkN::One;
+                match sides[..] {
+                    [a, b] if [a, b].iter().all(fks) => Some((def.name.clone(), [a, b])),
+                    _ => None,
+                }
+            })
+            .collect();
+        // Each junction row points at one entity on each side, but any number
+        // of rows may point at the same entity.
+        let many = |count: LinkN| if count.is_many() { count } else { LinkN::Many };
+        let mut dropped = HashSet::new();
+        for (name, [a, b]) in junctions {
+            let (a, b) = (&self.links[a], &self.links[b]);
+            let fields = self.def(&name).unwrap().fields.clone();
+            self.links.push(Link {
+                from: a.to.clone(),
+                from_count: many(a.from_count),
+                body: LinkBody {
+                    is_pk: false,
+                    symmetric: false,
+                },
+                to_count: many(b.from_count),
+                to: b.to.clone(),
+                label: Some(name.clone()),
+                priority: a.priority,
+                style: None,
+                color: None,
+                fields,
+                junction: true,
+            });
+            dropped.insert(name);
+        }
+        self.links.retain(|link| !dropped.contains(&link.from));
+        self.defs.retain(|def| !dropped.contains(&def.name));
+        self
+    }
+
+    /// Drops links declared more than once, keeping the first. Links count as
+    /// the same when their endpoints, cardinalities, body and label match.
+    pub fn dedup_links(mut self) -> Doc {
+        let mut kept: Vec<Link> = vec![];
+        for link in self.links {
+            if !kept.iter().any(|other| link.same_as(other)) {
+                kept.push(link);
+            }
+        }
+        self.links = kept;
+        self
+    }
+
+    /// The groups with only their entities in `kept`, leaving out the ones
+    /// that end up empty.
+    fn groups_within(&self, kept: &HashSet<&str>) -> Vec<Group> {
+        self.groups
+            .iter()
+            .map(|group| Group {
+                name: group.name.clone(),
+                defs: group
+                    .defs
+                    .iter()
+                    .filter(|name| kept.contains(name.as_str()))
+                    .cloned()
+                    .collect(),
+            })
+            .filter(|group| !group.defs.is_empty())
+            .collect()
+    }
+
+    /// Keeps only the entities matching `keep`. Links between a kept and a
+    /// dropped entity survive, with the dropped side replaced by a stub.
+    pub fn subset(&self, keep: impl Fn(&Def) -> bool) -> Doc {
+        let kept: HashSet<&str> = self
+            .defs
+            .iter()
+            .filter(|def| keep(def))
+            .map(|def| def.name.as_str())
+            .collect();
+        let mut defs: Vec<Def> = self
+            .defs
+            .iter()
+            .filter(|def| kept.contains(&*def.name))
+            .cloned()
+            .collect();
+        let mut stubs = HashSet::new();
+        // Whether a link or inheritance between `a` and `b` is kept, stubbing
+        // out whichever side isn't.
+        let mut connect = |a: &str, b: &str| {
+            let (a_kept, b_kept) = (kept.contains(a), kept.contains(b));
+            if !(a_kept || b_kept) {
+                return false;
+            }
+            for (name, is_kept) in [(a, a_kept), (b, b_kept)] {
+                if !is_kept && stubs.insert(name.to_owned()) {
+                    defs.push(Def {
+                        name: name.to_owned(),
+                        stub: true,
+                        ..Def::default()
+                    });
+                }
+            }
+            true
+        };
+        let links = self
+            .links
+            .iter()
+            .filter(|link| connect(&link.from, &link.to))
+            .cloned()
+            .collect();
+        let inherits = self
+            .inherits
+            .iter()
+            .filter(|inherit| connect(&inherit.child, &inherit.parent))
+            .cloned()
+            .collect();
+        let groups = self.groups_within(&kept);
+        Doc {
+            links,
+            defs,
+            groups,
+            version: self.version,
+            includes: self.includes.clone(),
+            inherits,
+            enums: self.enums.clone(),
+        }
+    }
+
+    /// Keeps only the entities matching `keep`, with the links and inheritance
+    /// among them. Unlike `subset`, nothing is stubbed out.
+    pub fn restrict(&self, keep: impl Fn(&Def) -> bool) -> Doc {
+        let kept: HashSet<&str> = self
+            .defs
+            .iter()
+            .filter(|def| keep(def))
+            .map(|def| def.name.as_str())
+            .collect();
+        let defs = self
+            .defs
+            .iter()
+            .filter(|def| kept.contains(&*def.name))
+            .cloned()
+            .collect();
+        let links = self
+            .links
+            .iter()
+            .filter(|link| kept.contains(&*link.from) && kept.contains(&*link.to))
+            .cloned()
+            .collect();
+        let inherits = self
+            .inherits
+            .iter()
+            .filter(|inherit| kept.contains(&*inherit.child) && kept.contains(&*inherit.parent))
+            .cloned()
+            .collect();
+        let groups = self.groups_within(&kept);
+        Doc {
+            links,
+            defs,
+            groups,
+            version: self.version,
+            includes: self.includes.clone(),
+            inherits,
+            enums: self.enums.clone(),
+        }
+    }
+
+    /// The entity `name` with the entities it is linked to or inherits from or
+    /// is inherited by, keeping only the links and inheritance touching it.
+    pub fn neighborhood(&self, name: &str) -> Doc {
+        let links: Vec<Link> = self
+            .links
+            .iter()
+            .filter(|link| link.from == name || link.to == name)
+            .cloned()
+            .collect();
+        let inherits: Vec<Inherit> = self
+            .inherits
+            .iter()
+            .filter(|inherit| inherit.child == name || inherit.parent == name)
+            .cloned()
+            .collect();
+        let mut kept: HashSet<&str> = HashSet::from([name]);
+        kept.extend(links.iter().flat_map(|link| [&*link.from, &*link.to]));
+        kept.extend(
+            inherits
+                .iter()
+                .flat_map(|inherit| [&*inherit.child, &*inherit.parent]),
+        );
+        let defs = self
+            .defs
+            .iter()
+            .filter(|def| kept.contains(&*def.name))
+            .cloned()
+            .collect();
+        let groups = self.groups_within(&kept);
+        Doc {
+            links,
+            defs,
+            groups,
+            version: self.version,
+            includes: self.includes.clone(),
+            inherits,
+            enums: self.enums.clone(),
+        }
+    }
+
+    /// Sorts entities by name and links by their endpoints, so that documents
+    /// saying the same thing are laid out the same. Fields keep their order
+    /// since it decides column order and `--first-field-pk`.
+    pub fn normalized(&self) -> Doc {
+        let mut defs = self.defs.clone();
+        defs.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut links = self.links.clone();
+        links.sort_by(|a, b| (&a.from, &a.to, &a.label).cmp(&(&b.from, &b.to, &b.label)));
+        let mut groups = self.groups.clone();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        for group in &mut groups {
+            group.defs.sort();
+        }
+        let mut includes = self.includes.clone();
+        includes.sort();
+        let mut inherits = self.inherits.clone();
+        inherits.sort_by(|a, b| (&a.child, &a.parent).cmp(&(&b.child, &b.parent)));
+        let mut enums = self.enums.clone();
+        enums.sort_by(|a, b| a.name.cmp(&b.name));
+        Doc {
+            links,
+            defs,
+            groups,
+            version: self.version,
+            includes,
+            inherits,
+            enums,
+        }
+    }
+
+    /// Splits the document into pages of at most `per_page` entities, keeping
+    /// connected entities close together.
+    pub fn pages(&self, per_page: usize) -> Vec<Doc> {
+        let mut order: Vec<&str> = vec![];
+        let mut seen = HashSet::new();
+        for def in &self.defs {
+            let mut queue = VecDeque::from([def.name.as_str()]);
+            while let Some(name) = queue.pop_front() {
+                if !seen.insert(name) {
+                    continue;
+                }
+                order.push(name);
+                for link in &self.links {
+                    if link.from == name {
+                        queue.push_back(&link.to);
+                    } else if link.to == name {
+                        queue.push_back(&link.from);
+                    }
+                }
+            }
+        }
+        order
+            .chunks(per_page)
+            .map(|page| self.subset(|def| page.contains(&&*def.name)))
+            .collect()
+    }
+}
+
+/// Several errors found in one go, so they can be fixed together.
+#[derive(Debug)]
+pub struct Errors(pub Vec<anyhow::Error>);
+
+impl Errors {
+    /// Fails with `errors` unless there are none.
+    pub fn check(errors: Vec<anyhow::Error>) -> Result<(), Errors> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Errors(errors))
+        }
+    }
+}
+
+impl Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let [error] = self.0.as_slice() {
+            return write!(f, "{error:#}");
+        }
+        write!(f, "{} errors:", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n\n{error:#}")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for Errors {}
+
+/// Parses a DiaLang document from its source text.
+pub fn parse_str(source: &str) -> anyhow::Result<Doc> {
+    let mut parser = MyParser::parse(Rule::document, source).map_err(|err| {
+        let (LineColLocation::Pos((line, col)) | LineColLocation::Span((line, col), _)) =
+            err.line_col;
+        anyhow!("Failed to parse input file at line {line}, column {col}\n{err}")
+    })?;
+    let doc = parser.next().unwrap();
+    let mut links: Vec<Link> = vec![];
+    let mut defs: Vec<Def> = vec![];
+    let mut def_lines = vec![];
+    let mut groups = vec![];
+    let mut version = None;
+    let mut includes = vec![];
+    let mut inherits = vec![];
+    let mut enums: Vec<Enum> = vec![];
+    let located = |err: Error| err.with_source(source);
+    // Each statement is parsed on its own, so that one mistake doesn't hide
+    // the ones after it.
+    let mut errors = vec![];
+    for tk in doc.into_inner() {
+        let statement = || -> anyhow::Result<()> {
+            match tk.as_rule() {
+                Rule::directive => {
+                    version = Some(Version::parse_at(tk).map_err(located)?);
+                }
+                Rule::include => {
+                    let path = literal(tk.into_inner().next().unwrap());
+                    includes.push(path.map_err(located)?);
+                }
+                Rule::inherit => {
+                    let mut inner = tk.into_inner();
+                    inherits.push(Inherit {
+                        child: inner.next_item().map_err(located)?,
+                        parent: inner.next_item().map_err(located)?,
+                    });
+                }
+                Rule::enum_decl => {
+                    let mut inner = tk.into_inner();
+                    // The `enum` keyword.
+                    inner.next();
+                    let name = inner.next_item().map_err(located)?;
+                    let variants: ParseResult<Vec<String>> = inner.map(String::parse_at).collect();
+                    enums.push(Enum {
+                        name,
+                        variants: variants.map_err(located)?,
+                    });
+                }
+                Rule::link => links.push(Link::parse_at(tk).map_err(located)?),
+                Rule::def => {
+                    let line = tk.line_col().0;
+                    defs.push(Def::parse_at(tk).map_err(located)?);
+                    def_lines.push(line);
+                }
+                Rule::group => {
+                    let mut inner = tk.into_inner();
+                    let name = literal(inner.next().unwrap()).map_err(located)?;
+                    let start = defs.len();
+                    for tk in inner {
+                        let line = tk.line_col().0;
+                        match Def::parse_at(tk) {
+                            Ok(def) => {
+                                defs.push(def);
+                                def_lines.push(line);
+                            }
+                            Err(err) => errors.push(located(err)),
+                        }
+                    }
+                    let names = defs[start..].iter().map(|def| def.name.clone()).collect();
+                    groups.push(Group { name, defs: names });
+                }
+                Rule::EOI => {}
+                rule => {
+                    return Err(Error::at(&tk, format!("Unexpected {rule:?}")).with_source(source));
+                }
+            }
+            Ok(())
+        };
+        if let Err(err) = statement() {
+            errors.push(err);
+        }
+    }
+    for (i, def) in defs.iter().enumerate() {
+        if let Some(j) = defs[..i].iter().position(|other| other.name == def.name) {
+            errors.push(anyhow!(
+                "Entity `{}` is defined twice, at lines {} and {}",
+                def.name,
+                def_lines[j],
+                def_lines[i]
+            ));
+        }
+    }
+    for (i, decl) in enums.iter().enumerate() {
+        if enums[..i].iter().any(|other| other.name == decl.name) {
+            errors.push(anyhow!("Enum `{}` is declared twice", decl.name));
+        } else if defs.iter().any(|def| def.name == decl.name) {
+            errors.push(anyhow!(
+                "Enum `{}` has the same name as an entity",
+                decl.name
+            ));
+        }
+    }
+    Errors::check(errors)?;
+    let mut doc = Doc {
+        links,
+        defs,
+        groups,
+        version,
+        includes,
+        inherits,
+        enums,
+    };
+    doc.resolve_enums();
+    Ok(doc)
+}
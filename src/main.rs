@@ -1,275 +1,1134 @@
 use std::{
-    fmt::Display,
-    io::{BufReader, BufWriter, Write},
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{BufWriter, IsTerminal, Write},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{Command, ExitCode, Stdio},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, bail};
-use notify::Watcher;
-use pest::{
-    Parser,
-    iterators::{Pair, Pairs},
+use dialang::emitters::{
+    Direction, EmitOptions, Indent, JSON_VERSION, Mode, NameStyle, Notation, ResolverLang, Theme,
+    ThemeName, emit_resolvers, emit_source, emit_source_comment, emit_stats, emit_summary_comment,
+};
+use dialang::{
+    Doc, Errors, FkCase, InheritanceStyle, LineStyle, LinkN, Naming, Version, identifier,
+    parse_str, types,
 };
+use notify::Watcher;
+
+mod selfcheck;
+mod validate;
+
+#[derive(argh::FromArgs)]
+/// DiaLang compiler
+struct Args {
+    #[argh(positional)]
+    /// inputs to merge, then the output file unless `-o` gives it. provide a
+    /// `.png`, `.svg` or `.pdf` to automatically pass through `dot`
+    files: Vec<PathBuf>,
+
+    #[argh(option, short = 'm', default = "Mode::default()")]
+    /// input mode
+    mode: Mode,
+
+    #[argh(option)]
+    /// read more inputs from a file listing one path per line, skipping
+    /// blank lines and `#` comments. `-` reads the list from stdin
+    files_from: Option<PathBuf>,
+
+    #[argh(option, short = 'o')]
+    /// output file, making every positional path an input. `-` is stdout
+    output: Option<PathBuf>,
+
+    #[argh(option, short = 'f')]
+    /// format to render diagrams to with dot's -T, instead of guessing it from
+    /// the output extension. `dot` or `raw` writes the diagram source
+    format: Option<String>,
+
+    #[argh(switch, short = 'w')]
+    /// enables watching file for changes
+    watch: bool,
+
+    #[argh(switch)]
+    /// refuse to overwrite an existing output file
+    no_clobber: bool,
+
+    #[argh(switch)]
+    /// clear the terminal before each rebuild in watch mode
+    watch_clear: bool,
+
+    #[argh(option, default = "200")]
+    /// how many milliseconds of quiet watch mode waits for after a change
+    /// before rebuilding, so that one save triggers one rebuild
+    debounce_ms: u64,
+
+    #[argh(option)]
+    /// shell command to run after each successful build in watch mode, with
+    /// the output path in $DIALANG_OUTPUT
+    on_rebuild: Option<String>,
+
+    #[argh(switch)]
+    /// treat the first field of each entity as its primary key instead of `id`
+    first_field_pk: bool,
+
+    #[argh(option, default = "String::from(\"id\")")]
+    /// name of the primary key field, `{entity}` standing for the entity's
+    /// name, e.g. `{entity}_pk`. defaults to `id`
+    pk_name: String,
+
+    #[argh(option, default = "String::from(\"_id\")")]
+    /// appended to the referenced entity to name foreign keys, defaults to
+    /// `_id`
+    fk_suffix: String,
+
+    #[argh(option, default = "FkCase::default()")]
+    /// how the referenced entity is written in foreign keys: lower (the
+    /// default), snake, pascal or camel
+    fk_case: FkCase,
+
+    #[argh(option, default = "32")]
+    /// how many files deep includes may go before giving up, 32 by default
+    max_include_depth: usize,
+
+    #[argh(option)]
+    /// whether entities repeat the fields they inherit (flatten, the default
+    /// of table outputs) or only show their own and refer to their parents
+    /// (reference, the default of the others)
+    inheritance_style: Option<InheritanceStyle>,
+
+    #[argh(switch)]
+    /// place DER relationship nodes on their own rank between entities
+    label_rank: bool,
+
+    #[argh(switch)]
+    /// draw optional relationship edges in gray and mandatory ones in black
+    color_participation: bool,
+
+    #[argh(switch)]
+    /// draw key fields above a divider, apart from the other fields
+    key_compartment: bool,
+
+    #[argh(switch)]
+    /// only draw primary keys and, in ORM diagrams, foreign keys
+    keys_only: bool,
+
+    #[argh(switch)]
+    /// draw junction entities as a many-to-many link between the two entities
+    /// they join
+    collapse_junctions: bool,
+
+    #[argh(switch)]
+    /// drop links declared more than once between the same entities
+    dedup: bool,
+
+    #[argh(switch)]
+    /// draw entities as plain boxes without fields
+    skeleton: bool,
+
+    #[argh(switch)]
+    /// add a legend explaining the cardinalities and markers to diagrams
+    legend: bool,
+
+    #[argh(switch)]
+    /// fail if any entity has no primary key
+    require_pk: bool,
+
+    #[argh(switch)]
+    /// warn about entity names that look like typos of each other
+    warn_similar_names: bool,
+
+    #[argh(switch)]
+    /// warn about entities that aren't linked to anything
+    warn_orphans: bool,
+
+    #[argh(switch)]
+    /// fail instead of only printing warnings
+    deny_warnings: bool,
+
+    #[argh(switch)]
+    /// fail if a declared fk column of a pk link has a different type than the
+    /// primary key it references
+    check_fk_types: bool,
+
+    #[argh(switch)]
+    /// fail on field types that are neither built in nor an entity name
+    strict_types: bool,
+
+    #[argh(switch)]
+    /// only parse and validate the input, printing OK instead of any output
+    check: bool,
+
+    #[argh(switch)]
+    /// prepend the input source as a comment to the generated output
+    embed_source: bool,
+
+    #[argh(switch)]
+    /// start the output with a comment counting entities and relationships
+    summary_header: bool,
+
+    #[argh(switch)]
+    /// print how many entities, fields and relationships the model has, and
+    /// its most linked entities, to stderr
+    stats: bool,
+
+    #[argh(option)]
+    /// split the diagram into numbered output files of at most N entities
+    paginate: Option<usize>,
+
+    #[argh(option)]
+    /// write one file per entity into this directory, each with the entity
+    /// and its direct neighbors, instead of a single output
+    output_dir: Option<PathBuf>,
+
+    #[argh(option)]
+    /// version of the JSON output format, defaults to the latest
+    json_version: Option<u32>,
+
+    #[argh(option)]
+    /// add N rows of sample data to each table of SQL output
+    emit_fixtures: Option<usize>,
+
+    #[argh(option)]
+    /// also write resolver stubs for the relationship fields of GraphQL
+    /// output, in ts or rust (async-graphql), next to the output file
+    with_resolvers: Option<ResolverLang>,
+
+    #[argh(option)]
+    /// how to display entity names: pascal, snake or title
+    name_style: Option<NameStyle>,
+
+    #[argh(option)]
+    /// indentation of generated code: a number of spaces or `tab`
+    indent: Option<Indent>,
+
+    #[argh(option, default = "Notation::default()")]
+    /// cardinality notation: min-max, chen, uml or crowsfoot-text
+    notation: Notation,
+
+    #[argh(option, from_str_fn(parse_symbol))]
+    /// override a cardinality symbol, e.g. `many=*`
+    symbol: Vec<(LinkN, String)>,
+
+    #[argh(option)]
+    /// only emit entities tagged with this `@kind`
+    only_kind: Vec<String>,
+
+    #[argh(option)]
+    /// leave out entities tagged with this `@kind`
+    exclude_kind: Vec<String>,
+
+    #[argh(option)]
+    /// only emit these entities, separated by commas, and the links among
+    /// them
+    only: Vec<String>,
+
+    #[argh(option)]
+    /// leave out these entities, separated by commas, and their links
+    exclude: Vec<String>,
+
+    #[argh(switch)]
+    /// print dot's layout diagnostics and timing when rendering images
+    trace_layout: bool,
 
-use crate::emitters::{emit_der, emit_orm};
+    #[argh(option)]
+    /// direction to lay diagrams out in: tb, lr, bt or rl
+    direction: Option<Direction>,
 
-#[derive(pest_derive::Parser)]
-#[grammar = "rules.pest"]
-struct MyParser;
+    #[argh(option)]
+    /// graphviz layout engine to use instead of `dot`, like `neato`, `fdp` or
+    /// `circo`
+    layout: Option<String>,
 
-macro_rules! ensure_rule {
-    ($tk:ident, $rule:expr) => {
-        if $tk.as_rule() != $rule {
-            return Err(Error {
-                cause: format!("Expected {:?}, got {:?}", $rule, $tk.as_rule()),
-            });
+    #[argh(option)]
+    /// graphviz engine to retry with when `dot` fails, like `neato`. can be
+    /// repeated to try several in order
+    engine_fallback: Vec<String>,
+
+    #[argh(option)]
+    /// extra argument to pass to `dot` when rendering images, like
+    /// `-Gdpi=300`. can be repeated
+    dot_arg: Vec<String>,
+
+    #[argh(option)]
+    /// graphviz `dot` binary to run, defaults to `$DIALANG_DOT` or else `dot`
+    /// from the PATH
+    dot_bin: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// make SVG output follow the viewer's light/dark color scheme
+    css_theming: bool,
+
+    #[argh(option, default = "ThemeName::default()")]
+    /// colors of diagrams: light or dark
+    theme: ThemeName,
+
+    #[argh(switch)]
+    /// same as `--theme dark`
+    dark: bool,
+
+    #[argh(option)]
+    /// font of diagram text, like `Helvetica`
+    font: Option<String>,
+
+    #[argh(switch)]
+    /// also write the DOT source of rendered images to a `.dot` file next to
+    /// them
+    keep_dot: bool,
+
+    #[argh(switch)]
+    /// print the input back in a canonical order instead of emitting it
+    normalize: bool,
+
+    #[argh(switch)]
+    /// print the parsed document to stderr before emitting it, to debug
+    /// parsing
+    dump_ast: bool,
+
+    #[argh(switch)]
+    /// run built-in snippets through the parser and report which constructs
+    /// still parse as they should, instead of reading any input
+    selfcheck: bool,
+
+    #[argh(switch)]
+    /// rewrite the input files in place in a canonical layout. with `--check`,
+    /// only list the ones that aren't
+    fmt: bool,
+}
+
+impl Args {
+    /// Input files and output file out of the arguments. With `-o`, or when
+    /// no output file gets written, every positional path is an input.
+    /// Otherwise the last of several is the output. Files listed by
+    /// `--files-from` come first.
+    fn paths(&self) -> anyhow::Result<(Vec<PathBuf>, Option<&Path>)> {
+        let listed = match &self.files_from {
+            Some(list) => listed_files(list)?,
+            None => vec![],
+        };
+        let mut positional: Vec<&Path> = self.files.iter().map(PathBuf::as_path).collect();
+        let output = if let Some(output) = &self.output {
+            Some(output.as_path())
+        } else if self.fmt || self.output_dir.is_some() || listed.len() + positional.len() <= 1 {
+            None
+        } else {
+            positional.pop()
+        };
+        let output = output.filter(|output| !is_stdin(output));
+        let mut inputs = listed;
+        inputs.extend(positional.into_iter().map(Path::to_owned));
+        if inputs.is_empty() {
+            bail!("No input files given");
         }
-    };
+        // A `.dia` output would most likely be an input about to be
+        // overwritten, so it has to be asked for with `-o`.
+        if let Some(output) = output
+            && self.output.is_none()
+            && !self.normalize
+            && output.extension().is_some_and(|ext| ext == "dia")
+        {
+            bail!(
+                "{} would be overwritten as the output, give the output with -o",
+                output.display()
+            );
+        }
+        Ok((inputs, output))
+    }
 }
 
-#[derive(Debug)]
-struct Error {
-    cause: String,
+fn parse_symbol(value: &str) -> Result<(LinkN, String), String> {
+    let (count, symbol) = value.split_once('=').ok_or("expected <count>=<symbol>")?;
+    let count = count
+        .parse()
+        .map_err(|_| format!("unknown count `{count}`"))?;
+    Ok((count, symbol.to_owned()))
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.cause)
+impl From<&Args> for Naming {
+    fn from(args: &Args) -> Self {
+        Naming {
+            pk_name: args.pk_name.clone(),
+            fk_suffix: args.fk_suffix.clone(),
+            fk_case: args.fk_case,
+        }
     }
 }
-impl std::error::Error for Error {}
 
-type ParseResult<T> = Result<T, Error>;
+impl From<&Args> for EmitOptions {
+    fn from(args: &Args) -> Self {
+        EmitOptions {
+            first_field_pk: args.first_field_pk,
+            naming: Naming::from(args),
+            label_rank: args.label_rank,
+            color_participation: args.color_participation,
+            key_compartment: args.key_compartment,
+            keys_only: args.keys_only,
+            skeleton: args.skeleton,
+            legend: args.legend,
+            json_version: args.json_version,
+            name_style: args.name_style,
+            indent: args.indent,
+            notation: args.notation,
+            symbols: args.symbol.clone(),
+            // A layout written into the graph would win over the `-K` of
+            // each engine `--engine-fallback` tries.
+            layout: args
+                .layout
+                .clone()
+                .filter(|_| args.engine_fallback.is_empty()),
+            direction: args.direction,
+            theme: Theme {
+                font_name: args.font.clone(),
+                ..Theme::from(if args.dark {
+                    ThemeName::Dark
+                } else {
+                    args.theme
+                })
+            },
+            fixtures: args.emit_fixtures,
+        }
+    }
+}
 
-trait Parse: Sized {
-    fn parse(tk: Token) -> ParseResult<Self>;
+fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
 }
-#[easy_ext::ext]
-impl Pairs<'_, Rule> {
-    fn next_item<T: Parse>(&mut self) -> ParseResult<T> {
-        T::parse(self.next().ok_or_else(|| Error {
-            cause: "Missing".into(),
-        })?)
-    }
+
+/// Whether `path` stands for standard input.
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str().is_empty() || path == Path::new("-")
 }
 
-#[derive(Debug, Clone, Copy)]
-enum LinkN {
-    One,
-    MaybeOne,
-    Many,
-    MaybeMany,
+fn read_source(path: &Path) -> anyhow::Result<String> {
+    if is_stdin(path) {
+        return std::io::read_to_string(std::io::stdin()).context("Failed to read standard input");
+    }
+    if is_url(path) {
+        return fetch(path.to_str().unwrap());
+    }
+    std::fs::read_to_string(path).context("Failed to open input file")
 }
 
-impl Display for LinkN {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                LinkN::One => "1",
-                LinkN::MaybeOne => "(0,1)",
-                LinkN::Many => "(1,N)",
-                LinkN::MaybeMany => "(0,N)",
-            }
-        )
+/// Downloads `url` with the `curl` binary, which spares the build an HTTP
+/// client and TLS stack.
+#[cfg(feature = "net")]
+fn fetch(url: &str) -> anyhow::Result<String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--fail", url])
+        .output()
+        .context("curl not found. can't fetch URLs")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to fetch {url}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
+    String::from_utf8(output.stdout).with_context(|| format!("{url} is not valid UTF-8"))
 }
 
-impl Parse for LinkN {
-    fn parse(tk: Token) -> ParseResult<Self> {
-        ensure_rule!(tk, Rule::link_n);
-        Ok(match tk.as_str().trim() {
-            "1" => Self::One,
-            "1?" => Self::MaybeOne,
-            "n" => Self::Many,
-            "n?" => Self::MaybeMany,
+#[cfg(not(feature = "net"))]
+fn fetch(url: &str) -> anyhow::Result<String> {
+    bail!("Can't fetch {url}: dialang was built without the `net` feature")
+}
 
-            _ => unreachable!("Unknown"),
-        })
+/// Opens `path` for writing. Anything that isn't a regular file, like a FIFO
+/// or `/dev/fd/3`, is opened as-is instead of being created and truncated.
+fn open_output(path: &Path) -> std::io::Result<File> {
+    match std::fs::metadata(path) {
+        Ok(meta) if !meta.is_file() => OpenOptions::new().write(true).open(path),
+        _ => File::create(path),
     }
 }
 
-impl Parse for String {
-    fn parse(tk: Token) -> ParseResult<Self> {
-        ensure_rule!(tk, Rule::name);
-        Ok(tk.as_str().to_owned())
+/// The `--files-from` list when it is standard input, which can only be read
+/// once.
+static STDIN_LIST: OnceLock<Result<String, String>> = OnceLock::new();
+
+/// Inputs listed in `list`, one per line. Paths are relative to the working
+/// directory, like those given as arguments. The list is read again on every
+/// call, so watch mode picks up files added to it.
+fn listed_files(list: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let text = if is_stdin(list) {
+        STDIN_LIST
+            .get_or_init(|| {
+                std::io::read_to_string(std::io::stdin())
+                    .map_err(|e| format!("Failed to read the file list from standard input: {e}"))
+            })
+            .clone()
+            .map_err(anyhow::Error::msg)?
+    } else {
+        std::fs::read_to_string(list)
+            .with_context(|| format!("Failed to read {}", list.display()))?
+    };
+    let paths: Vec<PathBuf> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect();
+    if is_stdin(list) && paths.iter().any(|path| is_stdin(path)) {
+        bail!("Standard input can't hold both the file list and an input");
     }
+    Ok(paths)
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct LinkBody {
-    is_pk: bool,
-}
+/// What watch mode last wrote to each output file, so rebuilds that wouldn't
+/// change it can skip running `dot` and writing.
+static LAST_OUTPUT: Mutex<BTreeMap<PathBuf, Vec<u8>>> = Mutex::new(BTreeMap::new());
 
-impl Parse for LinkBody {
-    fn parse(tk: Token) -> ParseResult<Self> {
-        ensure_rule!(tk, Rule::ARROW_BODY);
-        let is_pk = tk.as_str().contains('=');
-        Ok(Self { is_pk })
+/// Output extensions that get Graphviz modes rendered through `dot`.
+const IMAGE_FORMATS: [&str; 3] = ["svg", "png", "pdf"];
+
+/// Writes the output of `emit` to `path`, or stdout if there is none. Paths
+/// ending in one of `IMAGE_FORMATS` are rendered through `dot`.
+fn write_output(
+    args: &Args,
+    path: Option<&Path>,
+    emit: &dyn Fn(&mut dyn Write) -> std::io::Result<()>,
+) -> anyhow::Result<()> {
+    let sniffed = path
+        .and_then(|path| path.extension())
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| IMAGE_FORMATS.contains(ext));
+    let format = match args.format.as_deref() {
+        Some("dot" | "raw") => None,
+        Some(format) => Some(format),
+        None => sniffed,
+    }
+    .filter(|_| args.mode.is_graphviz() && !args.normalize);
+    let mut source = None;
+    if let Some(path) = path.filter(|_| args.watch) {
+        let mut bytes = vec![];
+        emit(&mut bytes)?;
+        if path.exists() && LAST_OUTPUT.lock().unwrap().get(path) == Some(&bytes) {
+            eprintln!("No changes to {}", path.display());
+            return Ok(());
+        }
+        source = Some(bytes);
+    }
+    let mut out: Box<dyn Write> = match path {
+        None => Box::new(std::io::stdout()),
+        Some(path) => {
+            if args.no_clobber && !args.watch && path.is_file() {
+                bail!("{} already exists, not overwriting it", path.display());
+            }
+            Box::new(open_output(path).map(BufWriter::new)?)
+        }
+    };
+    let source = match source {
+        Some(source) => source,
+        None if format.is_some() => {
+            let mut source = vec![];
+            emit(&mut source)?;
+            source
+        }
+        None => {
+            emit(&mut out)?;
+            vec![]
+        }
+    };
+    if let Some(format) = format {
+        if args.keep_dot {
+            let path = path
+                .context("--keep-dot needs an output file")?
+                .with_extension("dot");
+            std::fs::write(&path, &source)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        let image = render(args, &source, format)?;
+        if args.css_theming && format == "svg" {
+            let svg = String::from_utf8(image).context("dot wrote invalid utf-8")?;
+            out.write_all(theme_svg(&svg).as_bytes())?;
+        } else {
+            out.write_all(&image)?;
+        }
+    } else {
+        out.write_all(&source)?;
     }
+    out.flush()?;
+    if let Some(path) = path.filter(|_| args.watch) {
+        LAST_OUTPUT.lock().unwrap().insert(path.to_owned(), source);
+    }
+    Ok(())
 }
 
-#[derive(Debug, Clone)]
-struct Link {
-    from: String,
-    from_count: LinkN,
-    body: LinkBody,
-    to_count: LinkN,
-    to: String,
-    label: Option<String>,
+/// The `dot` binary from `--dot-bin`, `$DIALANG_DOT` or the PATH, in that
+/// order.
+fn dot_bin(args: &Args) -> PathBuf {
+    args.dot_bin
+        .clone()
+        .or_else(|| std::env::var_os("DIALANG_DOT").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("dot"))
 }
 
-impl Parse for Link {
-    fn parse(tk: Token) -> ParseResult<Self> {
-        ensure_rule!(tk, Rule::link);
-        let mut tk = tk.into_inner();
-        let from: String = tk.next_item()?;
-        let from_count: LinkN = tk.next_item()?;
-        let body: LinkBody = tk.next_item()?;
-        let to_count: LinkN = tk.next_item()?;
-        let to: String = tk.next_item()?;
-        Ok(Self {
-            from,
-            from_count,
-            body,
-            to_count,
-            to,
-            label: tk.next_item().ok(),
-        })
+/// Lays out `source` with Graphviz into a `format` image, trying each of
+/// `--engine-fallback` in turn when the `--layout` engine fails.
+fn render(args: &Args, source: &[u8], format: &str) -> anyhow::Result<Vec<u8>> {
+    let bin = dot_bin(args);
+    let layout = args.layout.as_deref().unwrap_or("dot");
+    let engines = std::iter::once(layout).chain(args.engine_fallback.iter().map(String::as_str));
+    let mut errors = String::new();
+    for engine in engines {
+        let mut dot = Command::new(&bin);
+        dot.arg(format!("-K{engine}")).arg(format!("-T{format}"));
+        if args.trace_layout {
+            dot.arg("-v");
+        }
+        dot.args(&args.dot_arg);
+        let started = Instant::now();
+        let mut dot = dot
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "Couldn't run {}, which is needed to output images. Is Graphviz installed?",
+                    bin.display()
+                )
+            })?;
+        // Feed dot from another thread while reading what it writes, so that
+        // neither side blocks on a full pipe on big diagrams.
+        let mut stdin = dot.stdin.take().unwrap();
+        let (written, output) = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(source));
+            let output = dot.wait_with_output();
+            (writer.join().unwrap(), output)
+        });
+        let output = output?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if args.trace_layout {
+            eprint!("{stderr}");
+            eprintln!("{engine} took {:?}", started.elapsed());
+        }
+        if output.status.success() {
+            written.context("Failed to write to dot")?;
+            if engine != layout {
+                eprintln!("Rendered with {engine}");
+            }
+            return Ok(output.stdout);
+        }
+        if !args.engine_fallback.is_empty() {
+            eprintln!("Warning: {engine} failed: {}", stderr.trim_end());
+        }
+        errors += &stderr;
     }
+    // Keep what dot choked on, so it can be tried again by hand.
+    let path = std::env::temp_dir().join(format!("dialang-{}.dot", std::process::id()));
+    let kept = match std::fs::write(&path, source) {
+        Ok(()) => format!("The DOT source is in {}", path.display()),
+        Err(err) => format!("Also failed to save the DOT source: {err}"),
+    };
+    bail!("Dot failed:\n{}\n{kept}", errors.trim_end());
 }
 
-#[derive(Debug, Clone)]
-struct Field {
-    field_type: String,
-    name: String,
+/// Colors swapped in by `theme_svg` when the viewer prefers a dark theme.
+const SVG_THEME_CSS: &str = r#"
+.dialang .node text, .dialang .edge text { fill: #000; }
+@media (prefers-color-scheme: dark) {
+  .dialang > g > polygon[fill="white"], .dialang .node polygon[fill="white"] { fill: #1e1e1e; }
+  .dialang [fill="gray"] { fill: #555; }
+  .dialang [stroke="black"] { stroke: #ccc; }
+  .dialang polygon[fill="black"] { fill: #ccc; }
+  .dialang .node text, .dialang .edge text { fill: #ccc; }
 }
+"#;
 
-#[derive(Debug, Clone)]
-pub struct Def {
-    name: String,
-    fields: Vec<Field>,
+/// Tags the root of a `dot -Tsvg` document with a `dialang` class and adds a
+/// stylesheet adapting it to the viewer's color scheme.
+fn theme_svg(svg: &str) -> String {
+    let Some(start) = svg.find("<svg") else {
+        return svg.to_owned();
+    };
+    let Some(end) = svg[start..].find('>').map(|i| start + i) else {
+        return svg.to_owned();
+    };
+    format!(
+        "{} class=\"dialang\">\n<style>{SVG_THEME_CSS}</style>{}",
+        &svg[..end],
+        &svg[end + 1..]
+    )
 }
 
-type Token<'a> = Pair<'a, Rule>;
+/// `out.png` becomes `out-{page}.png`.
+fn page_path(path: &Path, page: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}-{page}");
+    if let Some(ext) = path.extension() {
+        name = format!("{name}.{}", ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
 
-impl Parse for Def {
-    fn parse(tk: Token) -> ParseResult<Self> {
-        ensure_rule!(tk, Rule::def);
-        let mut def = tk.into_inner();
-        let name: String = def.next_item()?;
-        let mut fields = vec![];
-        for field in def {
-            let mut field = field.into_inner();
-            fields.push(Field {
-                field_type: field.next_item()?,
-                name: field.next_item()?,
-            })
+/// Applies `--only-kind`/`--exclude-kind`, stubbing out links to the entities
+/// left out.
+fn filter_kinds(doc: Doc, args: &Args) -> anyhow::Result<Doc> {
+    if args.only_kind.is_empty() && args.exclude_kind.is_empty() {
+        return Ok(doc);
+    }
+    let known: HashSet<&str> = doc
+        .defs
+        .iter()
+        .filter_map(|def| def.kind.as_deref())
+        .collect();
+    for kind in args.only_kind.iter().chain(&args.exclude_kind) {
+        if !known.contains(kind.as_str()) {
+            let mut known: Vec<&str> = known.into_iter().collect();
+            known.sort();
+            bail!("Unknown kind `{kind}`, known kinds: {}", known.join(", "));
         }
-        let def = Def { name, fields };
-        Ok(def)
     }
+    Ok(doc.subset(|def| {
+        let kind = def.kind.as_deref().unwrap_or_default();
+        let included = args.only_kind.is_empty() || args.only_kind.iter().any(|k| k == kind);
+        included && !args.exclude_kind.iter().any(|k| k == kind)
+    }))
 }
 
-mod emitters;
+/// Applies `--only`/`--exclude`, dropping the links to the entities left out.
+fn filter_entities(doc: Doc, args: &Args) -> anyhow::Result<Doc> {
+    if !args.only.is_empty() && !args.exclude.is_empty() {
+        bail!("--only and --exclude can't be used together");
+    }
+    let names = |list: &[String]| -> Vec<String> {
+        list.iter()
+            .flat_map(|names| names.split(','))
+            .map(|name| name.trim().to_owned())
+            .collect()
+    };
+    let (only, exclude) = (names(&args.only), names(&args.exclude));
+    if only.is_empty() && exclude.is_empty() {
+        return Ok(doc);
+    }
+    for name in only.iter().chain(&exclude) {
+        if doc.def(name).is_none() {
+            bail!("Unknown entity `{name}`");
+        }
+    }
+    Ok(doc.restrict(|def| {
+        let included = only.is_empty() || only.contains(&def.name);
+        included && !exclude.contains(&def.name)
+    }))
+}
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, strum::EnumString)]
-#[strum(serialize_all = "lowercase")]
-enum Mode {
-    #[default]
-    DER,
-    ORM,
+/// An input file, read and parsed.
+struct Input {
+    path: PathBuf,
+    source: String,
+    doc: Doc,
 }
 
-#[derive(argh::FromArgs)]
-/// DiaLang compiler
-struct Args {
-    #[argh(positional)]
-    input: PathBuf,
-    #[argh(option, short = 'm', default = "Mode::default()")]
-    /// input mode
-    mode: Mode,
+/// Reads input files along with the ones they include.
+struct Loader {
+    /// Whether to read the files named by `include` statements.
+    follow: bool,
+    /// Whether parse errors say which file they are in, which they always do
+    /// for included files.
+    named: bool,
+    /// How many files deep includes may go.
+    max_depth: usize,
+    /// Canonical paths of the files being included from, innermost last.
+    stack: Vec<PathBuf>,
+    /// Canonical paths of the files read so far, so a file included twice is
+    /// only read once.
+    seen: HashSet<PathBuf>,
+    /// Files in the order their entities should come in, included files
+    /// before the ones including them.
+    inputs: Vec<Input>,
+}
 
-    #[argh(positional)]
-    /// output file. provide a `.png` or `.svg` to automatically pass through `dot`
-    output: Option<PathBuf>,
+impl Loader {
+    fn new(follow: bool, named: bool, max_depth: usize) -> Self {
+        Loader {
+            follow,
+            named,
+            max_depth,
+            stack: vec![],
+            seen: HashSet::new(),
+            inputs: vec![],
+        }
+    }
 
-    #[argh(switch, short = 'w')]
-    /// enables watching file for changes
-    watch: bool,
+    fn load(&mut self, path: &Path) -> anyhow::Result<()> {
+        // Standard input and URLs can't be included, so they don't need to be
+        // told apart from files.
+        let real = !is_stdin(path) && !is_url(path);
+        let id = if real {
+            std::fs::canonicalize(path).context("Failed to open input file")?
+        } else {
+            path.to_owned()
+        };
+        if let Some(start) = self.stack.iter().position(|other| *other == id) {
+            let cycle: Vec<String> = self.stack[start..]
+                .iter()
+                .chain([&id])
+                .map(|path| path.display().to_string())
+                .collect();
+            bail!("Files include each other: {}", cycle.join(" -> "));
+        }
+        if self.stack.len() > self.max_depth {
+            let chain: Vec<String> = self
+                .stack
+                .iter()
+                .chain([&id])
+                .map(|path| path.display().to_string())
+                .collect();
+            bail!(
+                "Includes go more than {} files deep (see --max-include-depth): {}",
+                self.max_depth,
+                chain.join(" -> ")
+            );
+        }
+        if !self.seen.insert(id.clone()) {
+            return Ok(());
+        }
+        let source = read_source(path)?;
+        let doc = if self.named || !self.stack.is_empty() {
+            parse_str(&source).with_context(|| format!("In {}", path.display()))?
+        } else {
+            parse_str(&source)?
+        };
+        if self.follow && !doc.includes.is_empty() {
+            if is_url(path) {
+                bail!("Can't include files from a URL");
+            }
+            // Includes are relative to the file they are in, or to the
+            // working directory for standard input.
+            let dir = if real { path.parent() } else { None };
+            self.stack.push(id);
+            for include in &doc.includes {
+                let included = dir.unwrap_or(Path::new("")).join(include);
+                self.load(&included)
+                    .with_context(|| format!("Failed to include {}", included.display()))?;
+            }
+            self.stack.pop();
+        }
+        self.inputs.push(Input {
+            path: path.to_owned(),
+            source,
+            doc,
+        });
+        Ok(())
+    }
+}
+
+/// Combines the documents of several input files as if they were one, so
+/// links can point to entities of any file. Entities defined in more than one
+/// file are an error.
+fn merge(inputs: &[Input]) -> anyhow::Result<Doc> {
+    let mut merged = Doc {
+        links: vec![],
+        defs: vec![],
+        groups: vec![],
+        version: None,
+        includes: vec![],
+        inherits: vec![],
+        enums: vec![],
+    };
+    let mut def_paths = HashMap::new();
+    let mut enum_paths = HashMap::new();
+    for Input { path, doc, .. } in inputs {
+        for def in &doc.defs {
+            if let Some(other) = def_paths.insert(&def.name, path) {
+                bail!(
+                    "Entity `{}` is defined twice, in {} and {}",
+                    def.name,
+                    other.display(),
+                    path.display()
+                );
+            }
+        }
+        for decl in &doc.enums {
+            if let Some(other) = enum_paths.insert(&decl.name, path) {
+                bail!(
+                    "Enum `{}` is declared twice, in {} and {}",
+                    decl.name,
+                    other.display(),
+                    path.display()
+                );
+            }
+        }
+        merged.links.extend(doc.links.iter().cloned());
+        merged.defs.extend(doc.defs.iter().cloned());
+        merged.groups.extend(doc.groups.iter().cloned());
+        merged.version = merged.version.max(doc.version);
+        merged.includes.extend(doc.includes.iter().cloned());
+        merged.inherits.extend(doc.inherits.iter().cloned());
+        merged.enums.extend(doc.enums.iter().cloned());
+    }
+    merged.resolve_enums();
+    Ok(merged)
 }
 
-struct Doc {
-    links: Vec<Link>,
-    defs: Vec<Def>,
+/// Reads and parses all inputs, following their includes unless `follow` is
+/// false.
+fn load_inputs(args: &Args, follow: bool) -> anyhow::Result<Vec<Input>> {
+    let (paths, _) = args.paths()?;
+    let mut loader = Loader::new(follow, paths.len() > 1, args.max_include_depth);
+    for path in &paths {
+        loader.load(path)?;
+    }
+    for input in &loader.inputs {
+        if let Some(warning) = input.doc.version_warning() {
+            let name = if is_stdin(&input.path) {
+                "Standard input".to_owned()
+            } else {
+                input.path.display().to_string()
+            };
+            eprintln!("Warning: {name} {warning}");
+        }
+    }
+    Ok(loader.inputs)
 }
 
-fn parse_doc(path: &Path) -> anyhow::Result<Doc> {
-    let file = std::fs::read_to_string(path).context("Failed to open input file")?;
-    let mut parser =
-        MyParser::parse(Rule::document, &file).context("Failed to parse input file")?;
-    let doc = parser.next().unwrap();
-    let mut links: Vec<Link> = vec![];
-    let mut defs: Vec<Def> = vec![];
-    for tk in doc.into_inner() {
-        match tk.as_rule() {
-            Rule::link => links.push(Link::parse(tk)?),
-            Rule::def => defs.push(Def::parse(tk)?),
-            Rule::EOI => break,
-            _ => unreachable!("Got token {:?}", tk.as_rule()),
+/// Whether `source` has `#` comments, which don't survive parsing.
+fn has_comments(source: &str) -> bool {
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in source.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return true,
+            _ => {}
         }
     }
-    Ok(Doc { links, defs })
+    false
+}
+
+/// Rewrites each input file as `--normalize` prints it, or with `--check`
+/// fails listing the files that would change.
+fn format_files(args: &Args) -> anyhow::Result<()> {
+    if args.paths()?.1.is_some() {
+        bail!("--fmt rewrites the input files, drop the output file");
+    }
+    let mut unformatted = vec![];
+    for input in load_inputs(args, false)? {
+        let path = &input.path;
+        if has_comments(&input.source) {
+            bail!("{} has comments, which --fmt would drop", path.display());
+        }
+        let mut formatted = vec![];
+        emit_source(&mut formatted, &input.doc.normalized())?;
+        let formatted = String::from_utf8(formatted)?;
+        if formatted == input.source {
+            continue;
+        }
+        if args.check {
+            unformatted.push(path.display().to_string());
+        } else if is_stdin(path) {
+            print!("{formatted}");
+        } else {
+            std::fs::write(path, formatted)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    if !unformatted.is_empty() {
+        bail!("Not formatted: {}", unformatted.join(", "));
+    }
+    Ok(())
 }
 
 fn app(args: &Args) -> anyhow::Result<()> {
-    let doc = parse_doc(&args.input)?;
-    let emitter = match args.mode {
-        Mode::DER => emit_der,
-        Mode::ORM => emit_orm,
+    if args.fmt {
+        return format_files(args);
+    }
+    // A format guessed from the output extension is only dropped, one asked
+    // for can't be.
+    if args.format.is_some() && !args.mode.is_graphviz() {
+        bail!("-f only applies to graphviz modes");
+    }
+    if args.format.is_some() && args.normalize {
+        bail!("-f doesn't apply to --normalize, which writes DiaLang source");
+    }
+    let (_, output) = args.paths()?;
+    // Normalizing formats the files as written, keeping their includes.
+    let normalize = args.normalize && !args.check;
+    let inputs = load_inputs(args, !normalize)?;
+    let doc = merge(&inputs)?;
+    if args.dump_ast {
+        eprintln!("{doc:#?}");
+    }
+    if args.stats {
+        emit_stats(&mut std::io::stderr(), &doc)?;
+    }
+    // JSON writes the document as parsed, for tools that need what was
+    // written rather than what the other outputs make of it.
+    let raw = args.mode == Mode::Json;
+    let doc = if normalize || raw {
+        doc
+    } else {
+        Doc {
+            includes: vec![],
+            ..doc
+        }
     };
-    match &args.output {
-        None => emitter(&mut std::io::stdout(), &doc),
-        Some(path) => {
-            let mut out_file = std::fs::File::create(path).map(BufWriter::new)?;
-            if let Some(ext) = path.extension().and_then(|ext| ext.to_str())
-                && matches!(ext, "svg" | "png")
-            {
-                let mut dot = Command::new("dot")
-                    .args(&[&format!("-T{ext}")])
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped())
-                    .spawn()
-                    .context("dot not found. can't output images")?;
-                emitter(&mut dot.stdin.take().unwrap(), &doc)?;
-                if !dot.wait()?.success() {
-                    bail!("Dot failed.");
-                };
-
-                let mut cmd_output = dot.stdout.take().unwrap();
-                std::io::copy(&mut cmd_output, &mut out_file)?;
-                Ok(())
-            } else {
-                emitter(&mut out_file, &doc)
+    let doc = if args.dedup { doc.dedup_links() } else { doc };
+    let sources: Vec<&str> = inputs.iter().map(|input| input.source.as_str()).collect();
+    let source = sources.join("\n");
+    if normalize {
+        if let Some(input) = inputs.iter().find(|input| has_comments(&input.source)) {
+            bail!(
+                "{} has comments, which --normalize would drop",
+                input.path.display()
+            );
+        }
+        let doc = doc.normalized();
+        return write_output(args, output, &|f| emit_source(f, &doc));
+    }
+    // DER diagrams draw the fields of a link on its relationship instead.
+    let doc = if args.mode == Mode::Der || raw {
+        doc
+    } else {
+        doc.expand_associations(&Naming::from(args))?
+    };
+    // Prisma relates many-to-many models itself, without a junction model.
+    let doc = if args.mode.is_relational() {
+        doc.expand_many_to_many()?
+    } else {
+        doc
+    };
+    let tables = args.mode.is_relational() || args.mode == Mode::Prisma;
+    let doc = match args.inheritance_style {
+        _ if raw => doc,
+        Some(InheritanceStyle::Flatten) => doc.inline_inherited(),
+        None if tables => doc.inline_inherited(),
+        // Diagrams draw an edge to the parent, schemas need a foreign key.
+        Some(InheritanceStyle::Reference) if tables && args.mode != Mode::Orm => {
+            doc.reference_inherited()
+        }
+        _ => doc,
+    };
+    validate::validate(&doc, args)?;
+    // Filters naming entities that don't exist fail the check too.
+    let doc = filter_kinds(doc, args)?;
+    let doc = filter_entities(doc, args)?;
+    if args.check {
+        println!("OK");
+        return Ok(());
+    }
+    let doc = if args.collapse_junctions {
+        doc.collapse_junctions(args.first_field_pk, &Naming::from(args))
+    } else {
+        doc
+    };
+    if let Some(version) = args.json_version
+        && !(1..=JSON_VERSION).contains(&version)
+    {
+        bail!("Unsupported JSON version {version}, the latest is {JSON_VERSION}");
+    }
+    let opts = EmitOptions::from(args);
+    let emitter = args.mode.emitter();
+    let emitter = |f: &mut dyn Write, doc: &Doc| {
+        if args.summary_header
+            && let Some(comment) = args.mode.line_comment()
+        {
+            emit_summary_comment(f, doc, comment)?;
+        }
+        if args.embed_source
+            && let Some(comment) = args.mode.line_comment()
+        {
+            emit_source_comment(f, &source, comment)?;
+        }
+        emitter(f, doc, &opts)
+    };
+    if let Some(lang) = args.with_resolvers {
+        if args.mode != Mode::Graphql {
+            bail!("--with-resolvers only applies to -m graphql");
+        }
+        let path = output.context("--with-resolvers needs an output file to write next to")?;
+        let path = match lang {
+            ResolverLang::Ts => path.with_extension("resolvers.ts"),
+            // Rust modules can't have a dot in their name.
+            ResolverLang::Rust => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                path.with_file_name(format!("{stem}_resolvers.rs"))
             }
+        };
+        write_output(args, Some(&path), &|f| emit_resolvers(f, &doc, &opts, lang))?;
+    }
+    if let Some(dir) = &args.output_dir {
+        if output.is_some() || args.paginate.is_some() {
+            bail!("--output-dir writes its own files, drop the output file and --paginate");
         }
-    }?;
-    Ok(())
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let extension = match args.format.as_deref() {
+            Some(format) if args.mode.is_graphviz() && IMAGE_FORMATS.contains(&format) => format,
+            _ => args.mode.extension(),
+        };
+        let mut stems = HashSet::new();
+        for def in doc.defs.iter().filter(|def| !def.stub) {
+            // Quoted names can hold anything, so they mustn't reach outside
+            // of the directory.
+            if def.name.contains(['/', '\\']) {
+                bail!(
+                    "Can't name a file after `{}`, which has a path separator",
+                    def.name
+                );
+            }
+            let stem = identifier(&def.name);
+            if stem.is_empty() {
+                bail!("Can't name a file after `{}`", def.name);
+            }
+            if !stems.insert(stem.clone()) {
+                bail!(
+                    "`{}` would be written to {stem}.{extension} like another entity",
+                    def.name
+                );
+            }
+            let path = dir.join(format!("{stem}.{extension}"));
+            let neighborhood = doc.neighborhood(&def.name);
+            write_output(args, Some(&path), &|f| emitter(f, &neighborhood))?;
+        }
+        return Ok(());
+    }
+    match args.paginate {
+        None => write_output(args, output, &|f| emitter(f, &doc)),
+        Some(0) => bail!("--paginate needs at least one entity per page"),
+        Some(per_page) => {
+            let path = output.context("--paginate needs an output file")?;
+            for (i, page) in doc.pages(per_page).iter().enumerate() {
+                write_output(args, Some(&page_path(path, i + 1)), &|f| emitter(f, page))?;
+            }
+            Ok(())
+        }
+    }
 }
 
 fn watch(args: Args) -> anyhow::Result<()> {
+    let (paths, _) = args.paths()?;
+    let mut inputs = HashSet::new();
+    for path in &paths {
+        if is_url(path) {
+            bail!("Can't watch a URL for changes");
+        }
+        if is_stdin(path) {
+            bail!("Can't watch standard input for changes");
+        }
+        inputs.insert(std::fs::canonicalize(path).context("Failed to open input file")?);
+    }
+    // Editing the list rebuilds with the files now in it.
+    if let Some(list) = args.files_from.as_ref().filter(|list| !is_stdin(list)) {
+        inputs.insert(std::fs::canonicalize(list).context("Failed to open the file list")?);
+    }
+    inputs.extend(input_files(&args));
+    if args.no_clobber {
+        eprintln!("Warning: --no-clobber is ignored in watch mode");
+    }
+    let debounce = Duration::from_millis(args.debounce_ms);
     let (tx, rx) = std::sync::mpsc::channel();
     let mut watcher = notify::recommended_watcher(tx)?;
 
-    watcher.watch(Path::new("."), notify::RecursiveMode::NonRecursive)?;
+    let mut dirs = HashSet::new();
+    watch_dirs(&mut watcher, &mut dirs, &inputs)?;
 
-    let input = args.input.to_str().context("Not utf-8 path")?;
-    if let Err(e) = app(&args) {
-        eprintln!("Error: {e}")
-    }
-    for res in rx {
+    build(&args);
+    while let Ok(res) = rx.recv() {
         let ev = match res {
             Err(e) => {
                 eprintln!("Error watching stuff: {e}");
@@ -277,30 +1136,148 @@ fn watch(args: Args) -> anyhow::Result<()> {
             }
             Ok(ev) => ev,
         };
-        if !(ev.kind.is_create() || ev.kind.is_modify()) {
+        let changes_input = |ev: &notify::Event| {
+            (ev.kind.is_create() || ev.kind.is_modify())
+                && ev.paths.iter().any(|path| inputs.contains(path))
+        };
+        if !changes_input(&ev) {
             continue;
         }
+        // A single save can take several events, like writing a temporary
+        // file and renaming it over the input. Other files in the same
+        // directories being read or written don't delay the rebuild.
+        let mut quiet_from = Instant::now() + debounce;
+        while let Some(left) = quiet_from.checked_duration_since(Instant::now())
+            && let Ok(res) = rx.recv_timeout(left)
+        {
+            if res.is_ok_and(|ev| changes_input(&ev)) {
+                quiet_from = Instant::now() + debounce;
+            }
+        }
 
-        if !ev.paths.iter().any(|path| {
-            path.to_str()
-                .map(|path| path.contains(input))
-                .unwrap_or(false)
-        }) {
-            continue;
+        if args.watch_clear && std::io::stdout().is_terminal() {
+            print!("\x1b[2J\x1b[H");
         }
+        build(&args);
+        // Files may have been included since the last build.
+        inputs.extend(input_files(&args));
+        watch_dirs(&mut watcher, &mut dirs, &inputs)?;
+    }
+    Ok(())
+}
 
-        if let Err(e) = app(&args) {
-            eprintln!("Error: {e}")
+/// Builds once in watch mode, running `--on-rebuild` if that worked. Errors
+/// are only reported, so that the watcher keeps going.
+fn build(args: &Args) {
+    if let Err(e) = app(args) {
+        eprintln!("Error: {e:#}");
+        return;
+    }
+    let Some(hook) = &args.on_rebuild else {
+        return;
+    };
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(hook);
+    if let Ok((_, Some(output))) = args.paths() {
+        command.env("DIALANG_OUTPUT", output);
+    }
+    match command.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: --on-rebuild command failed with {status}"),
+        Err(e) => eprintln!("Warning: couldn't run --on-rebuild command: {e}"),
+    }
+}
+
+/// Canonical paths of the inputs and of the files they include, as far as
+/// they could be read.
+fn input_files(args: &Args) -> Vec<PathBuf> {
+    let Ok((paths, _)) = args.paths() else {
+        return vec![];
+    };
+    let mut loader = Loader::new(true, false, args.max_include_depth);
+    for path in &paths {
+        if loader.load(path).is_err() {
+            break;
+        }
+    }
+    loader.seen.into_iter().collect()
+}
+
+/// Starts watching the directories of `files` that aren't watched yet.
+///
+/// Watching the directory rather than the file keeps working when editors
+/// save by replacing the file.
+fn watch_dirs(
+    watcher: &mut impl Watcher,
+    dirs: &mut HashSet<PathBuf>,
+    files: &HashSet<PathBuf>,
+) -> notify::Result<()> {
+    for dir in files.iter().filter_map(|file| file.parent()) {
+        if dirs.insert(dir.to_owned()) {
+            watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
         }
     }
     Ok(())
 }
 
-fn main() {
-    let args = argh::from_env::<Args>();
-    if args.watch {
-        watch(args).unwrap()
+/// Like `argh::from_env`, but argh takes a lone `-` for an option, so it's
+/// passed on as the empty path, which also means standard input.
+/// Whether argh reads the argument after `arg` as the value of option `arg`.
+fn takes_value(cmd: &str, arg: &str) -> bool {
+    <Args as argh::FromArgs>::from_args(&[cmd], &[arg])
+        .is_err_and(|exit| exit.output.starts_with("No value provided for option"))
+}
+
+fn parse_args() -> Args {
+    let argv: Vec<String> = std::env::args().collect();
+    let cmd = Path::new(&argv[0])
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(&argv[0]);
+    // argh takes a lone `-` for an option, so positional ones, which stand for
+    // stdin or stdout, are passed as the empty path instead. Option values are
+    // read as they are.
+    let mut args: Vec<&str> = vec![];
+    let (mut value_next, mut options_ended) = (false, false);
+    for arg in &argv[1..] {
+        if value_next {
+            value_next = false;
+            args.push(arg);
+            continue;
+        }
+        args.push(if arg == "-" { "" } else { arg });
+        options_ended |= arg == "--";
+        value_next = !options_ended && arg.starts_with('-') && takes_value(cmd, arg);
+    }
+    match <Args as argh::FromArgs>::from_args(&[cmd], &args) {
+        Ok(args) => args,
+        Err(exit) => match exit.status {
+            Ok(()) => {
+                println!("{}", exit.output);
+                std::process::exit(0)
+            }
+            Err(()) => {
+                eprintln!("{}\nRun {cmd} --help for more information.", exit.output);
+                std::process::exit(1)
+            }
+        },
+    }
+}
+
+fn main() -> ExitCode {
+    let args = parse_args();
+    let result = if args.selfcheck {
+        selfcheck::selfcheck()
+    } else if args.watch {
+        watch(args)
     } else {
-        app(&args).unwrap()
+        app(&args)
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::FAILURE
+        }
     }
 }
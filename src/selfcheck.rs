@@ -0,0 +1,263 @@
+use anyhow::bail;
+
+use crate::*;
+
+/// A mini-document and what its parsed `Doc` should look like.
+struct Case {
+    name: &'static str,
+    source: &'static str,
+    check: fn(&Doc) -> bool,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "entity with fields",
+        source: "User {\n    id id\n    string name\n}\n",
+        check: |doc| {
+            let [def] = doc.defs.as_slice() else {
+                return false;
+            };
+            let fields: Vec<(&str, &str)> = def
+                .fields
+                .iter()
+                .map(|field| (field.field_type.as_str(), field.name.as_str()))
+                .collect();
+            def.name == "User" && fields == [("id", "id"), ("string", "name")]
+        },
+    },
+    Case {
+        name: "labeled link",
+        source: "User 1--n? Order: orders\n",
+        check: |doc| {
+            let [link] = doc.links.as_slice() else {
+                return false;
+            };
+            link.from == "User"
+                && link.from_count == LinkN::One
+                && link.to_count == LinkN::MaybeMany
+                && link.to == "Order"
+                && link.label.as_deref() == Some("orders")
+                && !link.body.is_pk
+        },
+    },
+    Case {
+        name: "primary key link",
+        source: "Item 1?==n Cart\n",
+        check: |doc| {
+            let [link] = doc.links.as_slice() else {
+                return false;
+            };
+            link.from_count == LinkN::MaybeOne
+                && link.to_count == LinkN::Many
+                && link.body.is_pk
+                && link.label.is_none()
+        },
+    },
+    Case {
+        name: "symmetric link",
+        source: "User n<->n User: friends\nUser 1--1 Profile\n",
+        check: |doc| {
+            let [friends, profile] = doc.links.as_slice() else {
+                return false;
+            };
+            friends.body.symmetric
+                && !friends.body.is_pk
+                && friends.from_count == LinkN::Many
+                && !profile.body.symmetric
+        },
+    },
+    Case {
+        name: "link style",
+        source: "User 1--n Session @style dashed @color \"#888888\"\n",
+        check: |doc| {
+            let [link] = doc.links.as_slice() else {
+                return false;
+            };
+            link.style == Some(LineStyle::Dashed) && link.color.as_deref() == Some("#888888")
+        },
+    },
+    Case {
+        name: "link with fields",
+        source: "Student n--n Course: Enrollment {\n    string grade\n}\n",
+        check: |doc| {
+            let [link] = doc.links.as_slice() else {
+                return false;
+            };
+            link.label.as_deref() == Some("Enrollment")
+                && link.fields.len() == 1
+                && link.fields[0].name == "grade"
+        },
+    },
+    Case {
+        name: "kind annotation",
+        source: "Log @kind audit {\n    id id\n}\n",
+        check: |doc| {
+            doc.defs
+                .first()
+                .is_some_and(|def| def.kind.as_deref() == Some("audit"))
+        },
+    },
+    Case {
+        name: "shape annotation",
+        source: "Gateway @shape box3d {\n    string url\n}\n",
+        check: |doc| {
+            doc.defs
+                .first()
+                .is_some_and(|def| def.shape.as_deref() == Some("box3d"))
+        },
+    },
+    Case {
+        name: "field examples",
+        source: "User {\n    string email example \"a\\\"@b.com\"\n    int age example 42\n}\n",
+        check: |doc| {
+            let examples: Vec<Option<&str>> = doc.defs[0]
+                .fields
+                .iter()
+                .map(|field| field.example.as_deref())
+                .collect();
+            examples == [Some("a\"@b.com"), Some("42")]
+        },
+    },
+    Case {
+        name: "composite primary key",
+        source: "Membership {\n    uuid user_uuid @pk\n    uuid team_uuid @pk\n    id id\n}\n",
+        check: |doc| {
+            let def = &doc.defs[0];
+            (0..def.fields.len())
+                .map(|i| def.is_pk(i, false, &Naming::default()))
+                .eq([true, true, false])
+        },
+    },
+    Case {
+        name: "list field",
+        source: "Post {\n    string[] tags\n    string title\n}\n",
+        check: |doc| {
+            let fields: Vec<(&str, bool)> = doc.defs[0]
+                .fields
+                .iter()
+                .map(|field| (field.field_type.as_str(), field.list))
+                .collect();
+            fields == [("string", true), ("string", false)]
+        },
+    },
+    Case {
+        name: "enum field",
+        source: "Account {\n    enum(active, suspended, closed) status\n    string name\n}\n",
+        check: |doc| {
+            let [status, name] = doc.defs[0].fields.as_slice() else {
+                return false;
+            };
+            status.is_enum()
+                && status.variants == ["active", "suspended", "closed"]
+                && !name.is_enum()
+        },
+    },
+    Case {
+        name: "named enum",
+        source: "enum Status { active, closed }\n\nAccount {\n    Status status\n}\n",
+        check: |doc| {
+            let status = &doc.defs[0].fields[0];
+            doc.enums.len() == 1
+                && status.is_named_enum()
+                && status.variants == ["active", "closed"]
+        },
+    },
+    Case {
+        name: "nullable field",
+        source: "User {\n    string nickname?\n    string name\n}\n",
+        check: |doc| {
+            let nullable: Vec<bool> = doc.defs[0]
+                .fields
+                .iter()
+                .map(|field| field.nullable)
+                .collect();
+            nullable == [true, false]
+        },
+    },
+    Case {
+        name: "unique field",
+        source: "User {\n    string email @unique @default \"''\"\n}\n",
+        check: |doc| {
+            let field = &doc.defs[0].fields[0];
+            field.unique && !field.nullable && field.default.as_deref() == Some("''")
+        },
+    },
+    Case {
+        name: "field doc",
+        source: "User {\n    string email @doc \"Where receipts go\"\n    string name\n}\n",
+        check: |doc| {
+            let docs: Vec<Option<&str>> = doc.defs[0]
+                .fields
+                .iter()
+                .map(|field| field.doc.as_deref())
+                .collect();
+            docs == [Some("Where receipts go"), None]
+        },
+    },
+    Case {
+        name: "comments",
+        source: "# header\nUser {\n    # inside\n    id id # trailing\n}\n",
+        check: |doc| doc.defs.len() == 1 && doc.defs[0].fields.len() == 1,
+    },
+    Case {
+        name: "quoted names",
+        source: "\"Order Item\" {\n    id id\n}\n\"Order Item\" n--1 Order: \"belongs \\\"to\\\"\"\n",
+        check: |doc| {
+            let [link] = doc.links.as_slice() else {
+                return false;
+            };
+            doc.defs[0].name == "Order Item"
+                && link.from == "Order Item"
+                && link.label.as_deref() == Some("belongs \"to\"")
+        },
+    },
+    Case {
+        name: "groups",
+        source: "group \"Auth\" {\n    User {\n        id id\n    }\n}\nLog {\n    id id\n}\n",
+        check: |doc| {
+            let [group] = doc.groups.as_slice() else {
+                return false;
+            };
+            doc.defs.len() == 2 && group.name == "Auth" && group.defs == ["User"]
+        },
+    },
+    Case {
+        name: "inheritance",
+        source: "User {\n    id id\n}\nAdmin {\n    int level\n}\nAdmin :> User\n",
+        check: |doc| {
+            let [inherit] = doc.inherits.as_slice() else {
+                return false;
+            };
+            inherit.child == "Admin" && inherit.parent == "User" && doc.links.is_empty()
+        },
+    },
+    Case {
+        name: "include",
+        source: "include \"auth.dia\"\nOrder n--1 User\n",
+        check: |doc| doc.includes == ["auth.dia"] && doc.defs.is_empty(),
+    },
+    Case {
+        name: "version directive",
+        source: "dialang 1.0\nUser {\n    id id\n}\n",
+        check: |doc| doc.version == Some(Version { major: 1, minor: 0 }),
+    },
+];
+
+/// Runs the built-in cases through the parser, reporting each one.
+pub fn selfcheck() -> anyhow::Result<()> {
+    let mut failed = 0;
+    for case in CASES {
+        let passed = match parse_str(case.source) {
+            Ok(doc) => (case.check)(&doc),
+            Err(_) => false,
+        };
+        println!("{} {}", if passed { "ok    " } else { "FAILED" }, case.name);
+        if !passed {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} grammar checks failed", CASES.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+/// Field types the code generating emitters know how to map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scalar {
+    Id,
+    String,
+    Int,
+    Float,
+    Bool,
+    Date,
+    Timestamp,
+    Uuid,
+}
+
+impl Scalar {
+    /// Classifies a DiaLang field type, accepting the usual spellings.
+    pub fn of(field_type: &str) -> Option<Scalar> {
+        Some(match field_type {
+            "id" => Scalar::Id,
+            "string" | "str" | "text" => Scalar::String,
+            "int" | "integer" => Scalar::Int,
+            "float" | "double" => Scalar::Float,
+            "bool" | "boolean" => Scalar::Bool,
+            "date" => Scalar::Date,
+            "ts" | "timestamp" | "datetime" => Scalar::Timestamp,
+            "uuid" => Scalar::Uuid,
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{anyhow, bail};
+
+use crate::{types::Scalar, *};
+
+/// Checks run over a parsed document before anything is emitted. Every check
+/// runs, and all of their errors are reported together.
+pub fn validate(doc: &Doc, args: &Args) -> anyhow::Result<()> {
+    let mut errors = vec![];
+    errors.extend(check_link_targets(doc).err());
+    errors.extend(check_inheritance(doc).err());
+    if args.mode.has_fk_columns() {
+        errors.extend(check_fk_names(doc, &Naming::from(args)).err());
+    }
+    if args.require_pk {
+        errors.extend(require_pk(doc, args.first_field_pk, &Naming::from(args)).err());
+    }
+    let mut warnings = vec![];
+    if args.warn_similar_names {
+        warnings.extend(similar_names(doc));
+    }
+    if args.warn_orphans {
+        warnings.extend(orphans(doc));
+    }
+    if args.check_fk_types {
+        errors.extend(check_fk_types(doc, args.first_field_pk, &Naming::from(args)).err());
+    }
+    if args.strict_types {
+        errors.extend(check_field_types(doc).err());
+    }
+    warnings.extend(deprecated_links(doc));
+    warnings.extend(unused_enums(doc));
+    for warning in &warnings {
+        eprintln!("Warning: {warning}");
+    }
+    if args.deny_warnings && !warnings.is_empty() {
+        errors.push(anyhow!("Warnings are errors with --deny-warnings"));
+    }
+    Ok(Errors::check(errors)?)
+}
+
+/// Named enums no field is typed after.
+fn unused_enums(doc: &Doc) -> Vec<String> {
+    let used = |name: &str| {
+        let mut fields = doc.defs.iter().flat_map(|def| &def.fields);
+        let mut link_fields = doc.links.iter().flat_map(|link| &link.fields);
+        fields.any(|field| field.field_type == name)
+            || link_fields.any(|field| field.field_type == name)
+    };
+    doc.enums
+        .iter()
+        .filter(|decl| !used(&decl.name))
+        .map(|decl| format!("Enum `{}` is never used", decl.name))
+        .collect()
+}
+
+/// Links that still point at a deprecated entity.
+fn deprecated_links(doc: &Doc) -> Vec<String> {
+    let mut warnings = vec![];
+    for link in &doc.links {
+        if link.from == link.to {
+            continue;
+        }
+        for (name, other) in [(&link.to, &link.from), (&link.from, &link.to)] {
+            if doc.def(name).is_some_and(|def| def.deprecated.is_some()) {
+                warnings.push(format!(
+                    "`{other}` is still linked to deprecated entity `{name}`"
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+/// Entities that take part in no link or inheritance, usually left behind
+/// by a refactor.
+fn orphans(doc: &Doc) -> Vec<String> {
+    let links = doc.links.iter().map(|link| [&link.from, &link.to]);
+    let inherits = doc
+        .inherits
+        .iter()
+        .map(|inherit| [&inherit.child, &inherit.parent]);
+    let connected: HashSet<&String> = links.chain(inherits).flatten().collect();
+    doc.defs
+        .iter()
+        .filter(|def| !connected.contains(&def.name))
+        .map(|def| format!("entity `{}` is not linked to anything", def.name))
+        .collect()
+}
+
+/// Fails if a link names an entity that was never defined, listing each
+/// unknown name once.
+fn check_link_targets(doc: &Doc) -> anyhow::Result<()> {
+    let mut unknown: Vec<&str> = vec![];
+    let links = doc.links.iter().map(|link| [&link.from, &link.to]);
+    let inherits = doc
+        .inherits
+        .iter()
+        .map(|inherit| [&inherit.child, &inherit.parent]);
+    for name in links.chain(inherits).flatten() {
+        if doc.def(name).is_none() && !unknown.contains(&name.as_str()) {
+            unknown.push(name);
+        }
+    }
+    if !unknown.is_empty() {
+        bail!("Links refer to undefined entities: {}", unknown.join(", "));
+    }
+    Ok(())
+}
+
+/// Fails if two links would give an entity foreign keys of the same name, or
+/// one would be named like a field. Only the key of a `==` link can be
+/// declared as a field, to spell out its type.
+fn check_fk_names(doc: &Doc, naming: &Naming) -> anyhow::Result<()> {
+    let mut collisions = vec![];
+    for def in &doc.defs {
+        let mut seen: Vec<String> = vec![];
+        for link in doc
+            .links
+            .iter()
+            .filter(|link| link.from == def.name && link.has_fk())
+        {
+            let fk = link.fk_name(&doc.links, naming);
+            if seen.contains(&fk) {
+                collisions.push(format!(
+                    "`{}` gets two `{fk}` columns, label its links to `{}` apart",
+                    def.name, link.to
+                ));
+            } else if !link.body.is_pk && def.fields.iter().any(|field| field.name == fk) {
+                collisions.push(format!(
+                    "field `{}.{fk}` collides with the foreign key of its link to `{}`",
+                    def.name, link.to
+                ));
+            }
+            seen.push(fk);
+        }
+    }
+    if !collisions.is_empty() {
+        bail!("Foreign key name collisions: {}", collisions.join("; "));
+    }
+    Ok(())
+}
+
+/// Fails if an entity ends up inheriting from itself.
+fn check_inheritance(doc: &Doc) -> anyhow::Result<()> {
+    for inherit in &doc.inherits {
+        let child = &inherit.child;
+        if *child == inherit.parent || doc.ancestors(&inherit.parent).contains(&child.as_str()) {
+            bail!(
+                "`{child}` inherits from itself through `{}`",
+                inherit.parent
+            );
+        }
+    }
+    Ok(())
+}
+
+fn require_pk(doc: &Doc, first_field_pk: bool, naming: &Naming) -> anyhow::Result<()> {
+    let missing: Vec<&str> = doc
+        .defs
+        .iter()
+        .filter(|def| {
+            let pk_link = doc
+                .links
+                .iter()
+                .any(|link| link.from == def.name && link.body.is_pk);
+            !(def.has_pk(first_field_pk, naming) || pk_link)
+        })
+        .map(|def| def.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        bail!("Entities without a primary key: {}", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// Checks that fk columns declared on the source of a pk link have the same
+/// type as the primary key they point to.
+fn check_fk_types(doc: &Doc, first_field_pk: bool, naming: &Naming) -> anyhow::Result<()> {
+    let mut mismatches = vec![];
+    for link in doc.links.iter().filter(|link| link.body.is_pk) {
+        let (Some(from), Some(to)) = (doc.def(&link.from), doc.def(&link.to)) else {
+            continue;
+        };
+        let fk_name = link.fk_name(&doc.links, naming);
+        let Some(fk) = from.fields.iter().find(|field| field.name == fk_name) else {
+            continue;
+        };
+        let mut pks = (0..to.fields.len()).filter(|&i| to.is_pk(i, first_field_pk, naming));
+        let (Some(pk), None) = (pks.next(), pks.next()) else {
+            continue;
+        };
+        let pk = &to.fields[pk];
+        if fk.list || !same_type(&fk.field_type, &pk.field_type) {
+            mismatches.push(format!(
+                "{}.{} is `{}` but {}.{} is `{}`",
+                from.name,
+                fk.name,
+                fk.type_name(),
+                to.name,
+                pk.name,
+                pk.type_name()
+            ));
+        }
+    }
+    if !mismatches.is_empty() {
+        bail!("Foreign key type mismatch: {}", mismatches.join("; "));
+    }
+    Ok(())
+}
+
+/// Fails on fields whose type is neither a [`Scalar`] nor an entity.
+fn check_field_types(doc: &Doc) -> anyhow::Result<()> {
+    let def_fields = doc.defs.iter().map(|def| (&def.name, &def.fields));
+    let link_fields = doc.links.iter().map(|link| {
+        let owner = link.label.as_ref().unwrap_or(&link.from);
+        (owner, &link.fields)
+    });
+    let mut unknown = vec![];
+    for (owner, fields) in def_fields.chain(link_fields) {
+        for field in fields {
+            let ty = &field.field_type;
+            if !field.is_enum() && Scalar::of(ty).is_none() && doc.def(ty).is_none() {
+                unknown.push(format!("{owner}.{} is `{ty}`", field.name));
+            }
+        }
+    }
+    if !unknown.is_empty() {
+        bail!("Unknown field types: {}", unknown.join("; "));
+    }
+    Ok(())
+}
+
+/// Whether two field types are spellings of the same type. `id` counts as
+/// an integer.
+fn same_type(a: &str, b: &str) -> bool {
+    let normalize = |ty| match Scalar::of(ty) {
+        Some(Scalar::Id) => Some(Scalar::Int),
+        scalar => scalar,
+    };
+    match (normalize(a), normalize(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Pairs of entity names that are a typo or two apart.
+fn similar_names(doc: &Doc) -> Vec<String> {
+    let names: Vec<String> = doc.defs.iter().map(|def| def.name.to_lowercase()).collect();
+    let mut warnings = vec![];
+    for (i, a) in names.iter().enumerate() {
+        for (j, b) in names.iter().enumerate().skip(i + 1) {
+            let max_distance = if a.len().min(b.len()) < 6 { 1 } else { 2 };
+            if a != b && levenshtein(a, b) <= max_distance {
+                warnings.push(format!(
+                    "entities `{}` and `{}` have very similar names, is one of them a typo?",
+                    doc.defs[i].name, doc.defs[j].name
+                ));
+            }
+        }
+    }
+    warnings
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,284 @@
+use std::{
+    path::PathBuf,
+    process::{Child, Command, Output, Stdio},
+    time::{Duration, Instant},
+};
+
+/// A fresh directory for the files of one test.
+fn scratch_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dialang-{test}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs dialang with `args`, failing the test if it takes longer than
+/// `timeout` instead of hanging it. Its output isn't read until it exits, so
+/// anything big has to go to a file.
+fn run(args: &[&str], dir: &PathBuf, timeout: Duration) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dialang"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let started = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if started.elapsed() > timeout {
+            child.kill().unwrap();
+            panic!("dialang {args:?} took longer than {timeout:?}");
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    child.wait_with_output().unwrap()
+}
+
+/// Starts dialang in watch mode with `args`. It has to be killed.
+fn watch(args: &[&str], dir: &PathBuf) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_dialang"))
+        .arg("-w")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+/// Waits for the file at `path` to contain `text`, polling it like an
+/// editor or a preview would, for up to 30 seconds.
+fn wait_for(path: &PathBuf, text: &str) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(30) {
+        if std::fs::read_to_string(path).is_ok_and(|contents| contents.contains(text)) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[cfg(unix)]
+#[test]
+fn big_diagrams_render_without_deadlocking() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch_dir("big");
+    // A stand-in for dot that echoes its input, so it writes as much as it
+    // reads and fills its stdout pipe long before it has read everything.
+    let dot = dir.join("fake-dot");
+    std::fs::write(&dot, "#!/bin/sh\nexec cat\n").unwrap();
+    std::fs::set_permissions(&dot, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let mut source = String::new();
+    for i in 0..500 {
+        source += &format!("Entity{i} {{\n    id id\n    string name\n}}\n");
+        if i > 0 {
+            source += &format!("Entity{i} n--1 Entity{}\n", i - 1);
+        }
+    }
+    std::fs::write(dir.join("big.dia"), source).unwrap();
+    let timeout = Duration::from_secs(30);
+    let dot = dot.to_str().unwrap();
+    let rendered = run(&["big.dia", "out.png", "--dot-bin", dot], &dir, timeout);
+    assert!(rendered.status.success(), "{rendered:?}");
+    let raw = run(&["big.dia", "-o", "out.dot"], &dir, timeout);
+    assert!(raw.status.success(), "{raw:?}");
+    let raw = std::fs::read(dir.join("out.dot")).unwrap();
+    // Far more than the 64KiB a pipe holds.
+    assert!(raw.len() > 1 << 18);
+    assert_eq!(std::fs::read(dir.join("out.png")).unwrap(), raw);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_and_exclude_filter_entities() {
+    let dir = scratch_dir("only");
+    let source = "\
+Users {
+    id id
+}
+Posts {
+    id id
+}
+Tags {
+    id id
+}
+Users n--1? Users: invited_by
+Posts n--1 Users
+Posts n--n Tags
+";
+    std::fs::write(dir.join("model.dia"), source).unwrap();
+    let timeout = Duration::from_secs(30);
+    // Entity names and link labels of the JSON output for `args`.
+    let kept = |args: &[&str]| -> (Vec<String>, Vec<String>) {
+        let args = [&["model.dia", "-m", "json", "-o", "-"], args].concat();
+        let output = run(&args, &dir, timeout);
+        assert!(output.status.success(), "{output:?}");
+        let json = String::from_utf8(output.stdout).unwrap();
+        let values = |key: &str| {
+            json.lines()
+                .filter_map(|line| line.strip_prefix(&format!("      \"{key}\": ")))
+                .map(|value| value.trim_end_matches(',').trim_matches('"').to_owned())
+                .collect()
+        };
+        (values("name"), values("label"))
+    };
+    assert_eq!(
+        kept(&["--only", "Users"]),
+        (vec!["Users".into()], vec!["invited_by".into()])
+    );
+    let (defs, labels) = kept(&["--only", "Users,Posts"]);
+    assert_eq!(defs, ["Users", "Posts"]);
+    assert_eq!(labels, ["invited_by", "null"]);
+    let (defs, labels) = kept(&["--exclude", "Tags"]);
+    assert_eq!(defs, ["Users", "Posts"]);
+    assert_eq!(labels, ["invited_by", "null"]);
+    let both = run(
+        &[
+            "model.dia",
+            "--only",
+            "Users",
+            "--exclude",
+            "Tags",
+            "-o",
+            "-",
+        ],
+        &dir,
+        timeout,
+    );
+    assert!(!both.status.success());
+    let stderr = String::from_utf8_lossy(&both.stderr);
+    assert!(
+        stderr.contains("--only and --exclude can't be used together"),
+        "{stderr}"
+    );
+    let unknown = run(&["model.dia", "--only", "User", "-o", "-"], &dir, timeout);
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("Unknown entity `User`"));
+    let check = run(&["model.dia", "--check", "--only", "Nope"], &dir, timeout);
+    assert!(!check.status.success());
+    assert!(String::from_utf8_lossy(&check.stderr).contains("Unknown entity `Nope`"));
+    let check = run(&["model.dia", "--check", "--only", "Users"], &dir, timeout);
+    assert_eq!(check.stdout, b"OK\n");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn files_from_lists_inputs_before_the_positional_ones() {
+    let dir = scratch_dir("files-from");
+    std::fs::write(dir.join("a.dia"), "A {\n    id id\n}\n").unwrap();
+    std::fs::write(dir.join("b.dia"), "B {\n    id id\n}\nB n--1 A\n").unwrap();
+    std::fs::write(dir.join("c.dia"), "C {\n    id id\n}\nC n--1 B\n").unwrap();
+    std::fs::write(dir.join("list.txt"), "# models\n\n  a.dia  \nb.dia\n").unwrap();
+    let timeout = Duration::from_secs(30);
+    let output = run(
+        &["--files-from", "list.txt", "c.dia", "-m", "sql", "out.sql"],
+        &dir,
+        timeout,
+    );
+    assert!(output.status.success(), "{output:?}");
+    let sql = std::fs::read_to_string(dir.join("out.sql")).unwrap();
+    let tables: Vec<&str> = sql
+        .lines()
+        .filter_map(|line| line.strip_prefix("CREATE TABLE "))
+        .collect();
+    assert_eq!(tables, ["\"A\" (", "\"B\" (", "\"C\" ("]);
+    let missing = run(&["--files-from", "nope.txt", "-o", "-"], &dir, timeout);
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("Failed to read nope.txt"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn formats_only_apply_to_graphviz_modes() {
+    let dir = scratch_dir("format");
+    std::fs::write(dir.join("model.dia"), "User {\n    id id\n}\n").unwrap();
+    let timeout = Duration::from_secs(30);
+    let explicit = run(
+        &["model.dia", "-m", "sql", "-f", "png", "-o", "out.sql"],
+        &dir,
+        timeout,
+    );
+    assert!(!explicit.status.success());
+    let stderr = String::from_utf8_lossy(&explicit.stderr);
+    assert!(
+        stderr.contains("-f only applies to graphviz modes"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out.sql").exists());
+    // A format guessed from the extension is just not used.
+    let sniffed = run(&["model.dia", "-m", "sql", "-o", "out.png"], &dir, timeout);
+    assert!(sniffed.status.success(), "{sniffed:?}");
+    let sql = std::fs::read_to_string(dir.join("out.png")).unwrap();
+    assert!(sql.starts_with("CREATE TABLE"), "{sql}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn dash_is_stdio_only_in_place_of_a_path() {
+    let dir = scratch_dir("dash");
+    let source = "User {\n    id id\n}\nPost {\n    id id\n}\nPost n--1 User\n";
+    std::fs::write(dir.join("model.dia"), source).unwrap();
+    let timeout = Duration::from_secs(30);
+    let output = run(
+        &["model.dia", "-m", "sql", "--fk-suffix", "-", "-o", "-"],
+        &dir,
+        timeout,
+    );
+    assert!(output.status.success(), "{output:?}");
+    let sql = String::from_utf8(output.stdout).unwrap();
+    assert!(sql.contains("    \"user-\" INTEGER NOT NULL,\n"), "{sql}");
+    let output = run(&["model.dia", "-", "-m", "sql"], &dir, timeout);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("CREATE TABLE"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn selfcheck_needs_no_input() {
+    let dir = scratch_dir("selfcheck");
+    let output = run(&["--selfcheck"], &dir, Duration::from_secs(30));
+    assert!(output.status.success(), "{output:?}");
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.lines().all(|line| line.starts_with("ok ")),
+        "{report}"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn watch_rebuilds_while_the_output_is_read() {
+    let dir = scratch_dir("watch");
+    std::fs::write(dir.join("model.dia"), "A {\n    id id\n}\n").unwrap();
+    let mut child = watch(&["model.dia", "-m", "sql", "-o", "out.sql"], &dir);
+    let output = dir.join("out.sql");
+    let first = wait_for(&output, "CREATE TABLE \"A\"");
+    std::fs::write(dir.join("model.dia"), "B {\n    id id\n}\n").unwrap();
+    // Reading the output more often than the debounce used to hold off the
+    // rebuild for as long as it went on.
+    let rebuilt = first && wait_for(&output, "CREATE TABLE \"B\"");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(first && rebuilt);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn watch_rereads_the_file_list() {
+    let dir = scratch_dir("watch-list");
+    std::fs::write(dir.join("a.dia"), "A {\n    id id\n}\n").unwrap();
+    std::fs::write(dir.join("b.dia"), "B {\n    id id\n}\n").unwrap();
+    std::fs::write(dir.join("list.txt"), "a.dia\n").unwrap();
+    let args = ["--files-from", "list.txt", "-m", "sql", "-o", "out.sql"];
+    let mut child = watch(&args, &dir);
+    let output = dir.join("out.sql");
+    let first = wait_for(&output, "CREATE TABLE \"A\"");
+    std::fs::write(dir.join("list.txt"), "a.dia\nb.dia\n").unwrap();
+    let rebuilt = first && wait_for(&output, "CREATE TABLE \"B\"");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(first && rebuilt);
+    std::fs::remove_dir_all(dir).unwrap();
+}
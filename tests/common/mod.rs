@@ -0,0 +1,22 @@
+use dialang::{Doc, parse_str};
+
+/// `source`, written indented inside a test, as if it started at the first
+/// column.
+pub fn dedent(source: &str) -> String {
+    let source = source.strip_prefix('\n').unwrap_or(source);
+    let indent = source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    source
+        .lines()
+        .map(|line| format!("{}\n", line.get(indent..).unwrap_or_default()))
+        .collect()
+}
+
+/// Parses `source` after dedenting it.
+pub fn parse(source: &str) -> Doc {
+    parse_str(&dedent(source)).unwrap()
+}
@@ -0,0 +1,851 @@
+mod common;
+
+use common::parse;
+use dialang::{
+    Doc, Naming,
+    emitters::{Direction, EmitOptions, Mode, Theme, ThemeName, emit_to_string},
+};
+
+fn emit(mode: Mode, doc: &Doc) -> String {
+    emit_to_string(mode, doc, &EmitOptions::default()).unwrap()
+}
+
+/// Ids of the relationship diamonds of a DER diagram.
+fn diamond_ids(dot: &str) -> Vec<&str> {
+    dot.lines()
+        .filter_map(|line| line.strip_prefix('\t'))
+        .filter(|line| !line.contains(" -- "))
+        .filter_map(|line| Some(line.split_once(" [label=<")?.0))
+        .collect()
+}
+
+#[test]
+fn links_between_the_same_pair_get_their_own_diamond() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        Post {
+            id id
+        }
+        Post n--1 User
+        Post n--1 User
+        ",
+    );
+    let dot = emit(Mode::Der, &doc);
+    assert_eq!(diamond_ids(&dot), ["Post_User_", "Post_User__2"]);
+    assert!(dot.contains("\tPost -- Post_User__2 "));
+    assert!(dot.contains("\tPost_User__2 -- User "));
+}
+
+#[test]
+fn html_special_characters_are_escaped_in_labels() {
+    let doc = parse(
+        r#"
+        "R&D <Lab>" {
+            id id
+        }
+        User {
+            id id
+        }
+        User n--1 "R&D <Lab>": "works <at>"
+        "#,
+    );
+    let der = emit(Mode::Der, &doc);
+    assert!(der.contains(r#"<TD colspan="2" bgcolor="gray">R&amp;D &lt;Lab&gt;</TD>"#));
+    assert!(der.contains("[label=<works &lt;at&gt;>]"));
+    assert!(der.contains("\t\"User_R&D <Lab>_works <at>\" -- \"R&D <Lab>\" "));
+    let orm = emit(Mode::Orm, &doc);
+    assert!(orm.contains(r#"<TD colspan="2" border="1">R&amp;D &lt;Lab&gt;</TD>"#));
+    assert!(orm.contains("\tUser -> \"R&D <Lab>\" "));
+}
+
+#[test]
+fn quotes_in_names_are_escaped_in_node_ids() {
+    let doc = parse(
+        r#"
+        "say \"hi\"" {
+            id id
+        }
+        "#,
+    );
+    let dot = emit(Mode::Der, &doc);
+    assert!(dot.lines().any(|line| line == r#""say \"hi\"" [label=<"#));
+}
+
+#[test]
+fn quotes_in_names_are_escaped_in_mermaid() {
+    let doc = parse(
+        r#"
+        "say \"hi\"" {
+            id id
+        }
+        "say \"hi\"" n--1 "say \"hi\"": "the \"same\""
+        "#,
+    );
+    let mermaid = emit(Mode::Mermaid, &doc);
+    assert!(mermaid.contains("    say_hi[\"say #quot;hi#quot;\"] {\n"));
+    assert!(mermaid.contains("    say_hi }|..|| say_hi : \"the #quot;same#quot;\"\n"));
+}
+
+#[test]
+fn foreign_keys_to_filtered_out_tables_are_not_enforced() {
+    let doc = parse(
+        "
+        User @kind core {
+            id id
+        }
+        Audit @kind log {
+            id id
+        }
+        Audit n--1 User
+        User n--1? Audit: last_audit
+        ",
+    );
+    let core = doc.subset(|def| def.kind.as_deref() == Some("core"));
+    let sql = emit(Mode::Sql, &core);
+    assert!(!sql.contains("CREATE TABLE \"Audit\""));
+    assert!(!sql.contains("REFERENCES \"Audit\""));
+    assert!(sql.contains(
+        "    -- References \"Audit\", which is not part of this output\n    \"audit_id\" INTEGER,\n"
+    ));
+    let only_user = doc.restrict(|def| def.name == "User");
+    assert!(!emit(Mode::Sql, &only_user).contains("audit_id"));
+    assert!(!emit(Mode::Orm, &only_user).contains("audit_id"));
+}
+
+#[test]
+fn self_links_get_unique_ids_and_loop_cleanly() {
+    let doc = parse(
+        "
+        Employee {
+            id id
+        }
+        Employee n--1? Employee
+        Employee n--1? Employee
+        Employee n--1? Employee: mentor
+        ",
+    );
+    let der = emit(Mode::Der, &doc);
+    assert_eq!(
+        diamond_ids(&der),
+        [
+            "Employee_Employee_",
+            "Employee_Employee__2",
+            "Employee_Employee_mentor"
+        ]
+    );
+    let doc = parse(
+        "
+        Employee {
+            id id
+        }
+        Employee n--1? Employee: manager
+        Employee n--1? Employee: mentor
+        ",
+    );
+    let orm = emit(Mode::Orm, &doc);
+    let loops: Vec<&str> = orm
+        .lines()
+        .filter(|line| line.contains("tailport=e, headport=e"))
+        .collect();
+    assert_eq!(loops.len(), 2);
+    assert!(orm.contains("+manager_id?"));
+    assert!(orm.contains("+mentor_id?"));
+}
+
+#[test]
+fn direction_sets_rankdir() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        ",
+    );
+    let opts = EmitOptions {
+        direction: Some("lr".parse::<Direction>().unwrap()),
+        ..EmitOptions::default()
+    };
+    for mode in [Mode::Der, Mode::Orm] {
+        let dot = emit_to_string(mode, &doc, &opts).unwrap();
+        assert_eq!(dot.lines().nth(1), Some("rankdir=LR;"));
+        assert!(!emit(mode, &doc).contains("rankdir"));
+    }
+}
+
+#[test]
+fn layout_is_only_written_when_asked_for() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        ",
+    );
+    let opts = EmitOptions {
+        layout: Some("neato".to_owned()),
+        ..EmitOptions::default()
+    };
+    for mode in [Mode::Der, Mode::Orm] {
+        let dot = emit_to_string(mode, &doc, &opts).unwrap();
+        assert_eq!(dot.lines().nth(1), Some("graph [layout=neato];"));
+        assert!(!emit(mode, &doc).contains("layout="));
+    }
+}
+
+/// Rows of the ORM table `name`, as `(marker, column)` pairs.
+fn orm_columns<'a>(dot: &'a str, name: &str) -> Vec<(&'a str, &'a str)> {
+    let start = dot
+        .find(&format!("\n{name} [label=<"))
+        .expect("table is missing");
+    let table = &dot[start..];
+    let table = &table[..table.find("</TABLE>").unwrap()];
+    let cells: Vec<&str> = table
+        .lines()
+        .filter_map(|line| line.strip_prefix("<TD align=\"LEFT\">"))
+        .filter_map(|line| line.strip_suffix("</TD>"))
+        .collect();
+    cells.chunks(2).map(|row| (row[0], row[1])).collect()
+}
+
+#[test]
+fn many_to_many_links_get_a_junction_table() {
+    let doc = parse(
+        "
+        Student {
+            id id
+        }
+        Course {
+            id id
+        }
+        Teacher {
+            id id
+        }
+        Student n--n Course
+        Course n--1 Teacher
+        Student n--n Student: friends
+        ",
+    )
+    .expand_many_to_many()
+    .unwrap();
+    let orm = emit(Mode::Orm, &doc);
+    assert_eq!(
+        orm_columns(&orm, "Student_Course"),
+        [("fk_pk", "+student_id"), ("fk_pk", "+course_id")]
+    );
+    assert!(orm.contains("\tStudent_Course -> Student "));
+    assert!(orm.contains("\tStudent_Course -> Course "));
+    assert_eq!(orm_columns(&orm, "Student"), [("pk", "+id")]);
+    assert_eq!(
+        orm_columns(&orm, "Course"),
+        [("pk", "+id"), ("fk", "+teacher_id")]
+    );
+    assert_eq!(
+        orm_columns(&orm, "friends"),
+        [("fk_pk", "+student_id"), ("fk_pk", "+friends_id")]
+    );
+    let sql = emit(Mode::Sql, &doc);
+    assert!(sql.contains("    PRIMARY KEY (\"student_id\", \"course_id\"),\n"));
+    assert!(sql.contains("    FOREIGN KEY (\"friends_id\") REFERENCES \"Student\" (\"id\")\n"));
+}
+
+#[test]
+fn quoted_names_make_quoted_node_ids() {
+    let doc = parse(
+        r#"
+        "Order Item" {
+            id id
+        }
+        Order {
+            id id
+        }
+        "Order Item" n--1 Order: "belongs to"
+        "#,
+    );
+    let der = emit(Mode::Der, &doc);
+    assert!(der.contains("\n\"Order Item\" [label=<"));
+    assert_eq!(diamond_ids(&der), [r#""Order Item_Order_belongs to""#]);
+    assert!(der.contains(r#"<TD colspan="2" bgcolor="gray">Order Item</TD>"#));
+    let sql = emit(Mode::Sql, &doc);
+    assert!(sql.contains("CREATE TABLE \"Order Item\" (\n"));
+    assert!(sql.contains("    \"order_id\" INTEGER NOT NULL,\n"));
+}
+
+#[test]
+fn nullable_unique_and_default_columns() {
+    let doc = parse(
+        r#"
+        User {
+            id id
+            string email @unique
+            int age?
+            string role @default "'member'"
+        }
+        "#,
+    );
+    assert_eq!(
+        orm_columns(&emit(Mode::Orm, &doc), "User"),
+        [("pk", "+id"), ("U", "+email"), ("", "+age?"), ("", "+role")]
+    );
+    let sql = emit(Mode::Sql, &doc);
+    assert!(sql.contains("    \"email\" TEXT NOT NULL UNIQUE,\n"));
+    assert!(sql.contains("    \"age\" INTEGER,\n"));
+    assert!(sql.contains("    \"role\" TEXT NOT NULL DEFAULT 'member',\n"));
+    let dbml = emit(Mode::Dbml, &doc);
+    assert!(dbml.contains("  email TEXT [unique]\n"));
+    assert!(dbml.contains("  age INTEGER [null]\n"));
+    assert!(dbml.contains("  role TEXT [default: `'member'`]\n"));
+    let pydantic = emit(Mode::Pydantic, &doc);
+    assert!(pydantic.contains("    age: Optional[int] = None\n"));
+    assert!(pydantic.contains("    role: str = \"member\"\n"));
+    let mongoose = emit(Mode::Mongoose, &doc);
+    assert!(mongoose.contains("  age: { type: Number },\n"));
+    assert!(mongoose.contains("  role: { type: String, required: true, default: \"member\" },\n"));
+}
+
+#[test]
+fn dark_theme_colors_diagrams() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        Post {
+            id id
+        }
+        Post n--1 User
+        ",
+    );
+    let opts = EmitOptions {
+        theme: Theme::from("dark".parse::<ThemeName>().unwrap()),
+        ..EmitOptions::default()
+    };
+    let der = emit_to_string(Mode::Der, &doc, &opts).unwrap();
+    assert!(der.contains("graph [bgcolor=\"#1e1e1e\"];\n"));
+    assert!(der.contains("node [color=\"#a0a0a0\", fontcolor=\"#e0e0e0\"];\n"));
+    assert!(der.contains(r##"<TD colspan="2" bgcolor="#3c3c3c">User</TD>"##));
+    assert!(!der.contains("bgcolor=\"gray\""));
+    let orm = emit_to_string(Mode::Orm, &doc, &opts).unwrap();
+    assert!(orm.contains("edge [color=\"#a0a0a0\", fontcolor=\"#e0e0e0\"];\n"));
+    assert!(orm.contains(r##"cellpadding="1" bgcolor="#1e1e1e">"##));
+    let light = emit(Mode::Der, &doc);
+    assert!(light.contains(r#"<TD colspan="2" bgcolor="gray">User</TD>"#));
+    assert!(!light.contains("bgcolor=\"#"));
+}
+
+#[test]
+fn theme_font_applies_to_all_text() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        ",
+    );
+    let opts = EmitOptions {
+        theme: Theme {
+            font_name: Some("Inter".to_owned()),
+            ..Theme::light()
+        },
+        ..EmitOptions::default()
+    };
+    for mode in [Mode::Der, Mode::Orm] {
+        let dot = emit_to_string(mode, &doc, &opts).unwrap();
+        for kind in ["graph", "node", "edge"] {
+            assert!(dot.contains(&format!("{kind} [fontname=\"Inter\"];\n")));
+        }
+    }
+}
+
+#[test]
+fn link_fields_go_on_the_relationship() {
+    let source = "
+        Student {
+            id id
+        }
+        Course {
+            id id
+        }
+        Student n==n Course: enrollment {
+            date enrolled_on
+        }
+        ";
+    let der = emit(Mode::Der, &parse(source));
+    assert!(der.contains(
+        "\tStudent_Course_enrollment [label=<<TABLE border=\"0\" cellborder=\"0\" \
+         cellspacing=\"0\"><TR><TD>enrollment</TD></TR>\n<TR><TD>date enrolled_on</TD></TR>\n"
+    ));
+    let doc = parse(source)
+        .expand_associations(&Naming::default())
+        .unwrap();
+    let orm = emit(Mode::Orm, &doc);
+    assert_eq!(
+        orm_columns(&orm, "enrollment"),
+        [
+            ("", "+enrolled_on"),
+            ("fk_pk", "+student_id"),
+            ("fk_pk", "+course_id")
+        ]
+    );
+    assert!(orm.contains("\tenrollment -> Student "));
+    assert!(orm.contains("\tenrollment -> Course "));
+}
+
+#[test]
+fn optional_links_make_nullable_foreign_keys() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        Team {
+            id id
+        }
+        Post {
+            id id
+        }
+        Post n--1 User
+        Post n--1? Team
+        ",
+    );
+    assert_eq!(
+        orm_columns(&emit(Mode::Orm, &doc), "Post"),
+        [("pk", "+id"), ("fk", "+user_id"), ("fk", "+team_id?")]
+    );
+    let sql = emit(Mode::Sql, &doc);
+    assert!(sql.contains("    \"user_id\" INTEGER NOT NULL,\n"));
+    assert!(sql.contains("    \"team_id\" INTEGER,\n"));
+    let dbml = emit(Mode::Dbml, &doc);
+    assert!(dbml.contains("  user_id INTEGER\n"));
+    assert!(dbml.contains("  team_id INTEGER [null]\n"));
+}
+
+#[test]
+fn output_is_the_same_on_every_run() {
+    let source = r#"
+        enum Status { active, closed }
+        group "Content" {
+            Post {
+                id id
+                string title
+                Status status
+            }
+            Tag {
+                id id
+            }
+        }
+        User {
+            id id
+            enum(admin, member) role
+        }
+        Admin {
+        }
+        Team {
+            id id
+        }
+        Admin :> User
+        Post n--1 User: author
+        Post n--1? User: editor
+        Post n--n Tag
+        User n--1? Team
+        User n<->n User: friends
+        "#;
+    let modes = [
+        Mode::Der,
+        Mode::Orm,
+        Mode::Json,
+        Mode::EsMapping,
+        Mode::Prose,
+        Mode::Pydantic,
+        Mode::Mongoose,
+        Mode::GraphMl,
+        Mode::Mermaid,
+        Mode::Outline,
+        Mode::Sql,
+        Mode::Dbml,
+        Mode::Ts,
+        Mode::Prisma,
+        Mode::Graphql,
+    ];
+    for mode in modes {
+        let first = emit(mode, &parse(source));
+        for _ in 0..10 {
+            assert_eq!(emit(mode, &parse(source)), first, "{mode:?} output changed");
+        }
+    }
+}
+
+/// The lines of the Prisma model `name`, without the braces.
+fn prisma_model<'a>(schema: &'a str, name: &str) -> Vec<&'a str> {
+    let start = schema
+        .find(&format!("model {name} {{\n"))
+        .expect("model is missing");
+    schema[start..]
+        .lines()
+        .skip(1)
+        .take_while(|line| *line != "}")
+        .map(str::trim)
+        .collect()
+}
+
+#[test]
+fn prisma_relates_both_sides_of_a_link() {
+    let doc = parse(
+        "
+        User {
+            id id
+            string name
+        }
+        Post {
+            id id
+        }
+        Tag {
+            id id
+        }
+        Post n--1 User
+        Post n--n Tag
+        ",
+    );
+    let schema = emit(Mode::Prisma, &doc);
+    assert_eq!(
+        prisma_model(&schema, "User"),
+        ["id Int @id", "name String", "posts Post[]"]
+    );
+    assert_eq!(
+        prisma_model(&schema, "Post"),
+        [
+            "id Int @id",
+            "user_id Int",
+            "user User @relation(fields: [user_id], references: [id])",
+            "tags Tag[]"
+        ]
+    );
+    assert_eq!(prisma_model(&schema, "Tag"), ["id Int @id", "posts Post[]"]);
+}
+
+#[test]
+fn symmetric_links_are_drawn_both_ways_without_a_foreign_key() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        Team {
+            id id
+        }
+        User n<->n User: friends
+        User n--1 Team
+        ",
+    );
+    assert!(doc.links[0].body.symmetric);
+    assert!(!doc.links[1].body.symmetric);
+    let doc = doc.expand_many_to_many().unwrap();
+    let orm = emit(Mode::Orm, &doc);
+    assert_eq!(
+        orm_columns(&orm, "User"),
+        [("pk", "+id"), ("fk", "+team_id")]
+    );
+    assert!(orm.contains("\tUser -> User [dir=both, "));
+    assert!(orm.contains("\tUser -> Team [headlabel="));
+    assert!(!orm.contains("friends_id"));
+    assert!(!emit(Mode::Sql, &doc).contains("CREATE TABLE \"friends\""));
+}
+
+#[test]
+fn graphql_types_and_relation_fields() {
+    let doc = parse(
+        r#"
+        User {
+            id id
+            string name
+            int age?
+            float score
+            bool active
+            string nick @deprecated "use name"
+        }
+        Post {
+            id id
+        }
+        Team {
+            id id
+        }
+        Empty {
+        }
+        User 1--n Post
+        User n--1? Team
+        "#,
+    );
+    assert_eq!(
+        emit(Mode::Graphql, &doc),
+        [
+            "type User {",
+            "  id: ID!",
+            "  name: String!",
+            "  age: Int",
+            "  score: Float!",
+            "  active: Boolean!",
+            "  nick: String! @deprecated(reason: \"use name\")",
+            "  posts: [Post!]!",
+            "  team: Team",
+            "}",
+            "",
+            "type Post {",
+            "  id: ID!",
+            "}",
+            "",
+            "type Team {",
+            "  id: ID!",
+            "}",
+            "",
+            "type Empty {",
+            "  _empty: Boolean",
+            "}",
+            "",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn graphql_deprecates_fields_linking_to_deprecated_entities() {
+    let doc = parse(
+        r#"
+        Legacy @deprecated "gone" {
+            id id
+        }
+        User {
+            id id
+        }
+        User n--1 Legacy
+        "#,
+    );
+    let schema = emit(Mode::Graphql, &doc);
+    assert!(schema.starts_with("# Deprecated: gone\ntype Legacy {\n"));
+    assert!(schema.contains("  legacy: Legacy! @deprecated(reason: \"gone\")\n"));
+}
+
+#[test]
+fn enum_fields_list_their_values_everywhere() {
+    let doc = parse(
+        r#"
+        User {
+            id id
+            enum(active, suspended, "on hold") status
+        }
+        "#,
+    );
+    let der = emit(Mode::Der, &doc);
+    assert!(der.contains("<TR><TD>enum</TD><TD>status</TD></TR>\n"));
+    let sql = emit(Mode::Sql, &doc);
+    assert!(sql.contains("    \"status\" TEXT NOT NULL,\n"));
+    assert!(sql.contains("    CHECK (\"status\" IN ('active', 'suspended', 'on hold'))\n"));
+    let dbml = emit(Mode::Dbml, &doc);
+    assert!(dbml.starts_with("enum User_status {\n  active\n  suspended\n  \"on hold\"\n}\n"));
+    assert!(dbml.contains("  status User_status\n"));
+    let graphql = emit(Mode::Graphql, &doc);
+    assert!(graphql.starts_with("enum UserStatus {\n  active\n  suspended\n  on_hold\n}\n"));
+    assert!(graphql.contains("  status: UserStatus!\n"));
+    let prisma = emit(Mode::Prisma, &doc);
+    assert!(prisma.starts_with("enum UserStatus {\n  active\n  suspended\n  on_hold\n}\n"));
+    assert!(prisma.contains("  status UserStatus\n"));
+    let pydantic = emit(Mode::Pydantic, &doc);
+    assert!(pydantic.contains(
+        "class UserStatus(str, Enum):\n    active = \"active\"\n    suspended = \"suspended\"\n    \
+         on_hold = \"on hold\"\n"
+    ));
+    assert!(pydantic.contains("    status: UserStatus\n"));
+    let ts = emit(Mode::Ts, &doc);
+    assert!(ts.contains("export type UserStatus = \"active\" | \"suspended\" | \"on hold\";\n"));
+    assert!(ts.contains("  status: UserStatus;\n"));
+    let mongoose = emit(Mode::Mongoose, &doc);
+    assert!(mongoose.contains(
+        "  status: { type: String, enum: [\"active\", \"suspended\", \"on hold\"], required: true },\n"
+    ));
+}
+
+#[test]
+fn named_enums_are_declared_once() {
+    let doc = parse(
+        "
+        enum Role { admin, member }
+        User {
+            id id
+            Role role
+        }
+        Team {
+            id id
+            Role[] roles
+        }
+        ",
+    );
+    let prisma = emit(Mode::Prisma, &doc);
+    assert_eq!(prisma.matches("enum Role {").count(), 1);
+    assert!(prisma.contains("  role Role\n"));
+    assert!(prisma.contains("  roles Role[]\n"));
+    let graphql = emit(Mode::Graphql, &doc);
+    assert_eq!(graphql.matches("enum Role {").count(), 1);
+    assert!(graphql.contains("  roles: [Role!]!\n"));
+}
+
+#[test]
+fn duplicate_links_are_emitted_once() {
+    let doc = parse(
+        "
+        User {
+            id id
+        }
+        Post {
+            id id
+        }
+        Post n--1 User: author
+        Post n--1 User: author
+        Post n--1? User: author
+        Post n--1 User: editor
+        ",
+    )
+    .dedup_links();
+    let labels: Vec<(Option<&str>, bool)> = doc
+        .links
+        .iter()
+        .map(|link| (link.label.as_deref(), link.to_count.is_optional()))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            (Some("author"), false),
+            (Some("author"), true),
+            (Some("editor"), false)
+        ]
+    );
+    let der = emit(Mode::Der, &doc);
+    assert_eq!(
+        diamond_ids(&der),
+        ["Post_User_author", "Post_User_author_2", "Post_User_editor"]
+    );
+    let orm = emit(Mode::Orm, &doc);
+    assert_eq!(orm.matches("\tPost -> User ").count(), 3);
+}
+
+#[test]
+fn link_style_and_color_go_on_their_edges_only() {
+    let doc = parse(
+        r##"
+        User {
+            id id
+        }
+        Session {
+            id id
+        }
+        Post {
+            id id
+        }
+        Session n--1 User @style dashed @color "#888888"
+        Post n--1 User
+        "##,
+    );
+    let styled = ", style=dashed, color=\"#888888\"];";
+    let der = emit(Mode::Der, &doc);
+    for edge in ["\tSession -- Session_User_ ", "\tSession_User_ -- User "] {
+        let line = der.lines().find(|line| line.starts_with(edge)).unwrap();
+        assert!(line.ends_with(styled), "{line}");
+    }
+    assert_eq!(der.matches("style=dashed").count(), 2);
+    let orm = emit(Mode::Orm, &doc);
+    let (session, post) = orm.split_once("\tPost -> User ").unwrap();
+    assert!(session.contains("\tSession -> User "));
+    assert!(session.trim_end().ends_with(styled));
+    assert!(!post.contains("style="));
+}
+
+#[test]
+fn pydantic_names_are_python_identifiers() {
+    let doc = parse(
+        r#"
+        class {
+            id id
+            enum(None, class, "1st") kind
+            string from
+            string import? @default "'x'"
+            int 2nd?
+        }
+        Other {
+            id id
+        }
+        Other n--1 class
+        "#,
+    );
+    let pydantic = emit(Mode::Pydantic, &doc);
+    assert!(pydantic.contains("from pydantic import BaseModel, Field\n"));
+    assert!(pydantic.contains(
+        "class classKind(str, Enum):\n    None_ = \"None\"\n    class_ = \"class\"\n    \
+         n_1st = \"1st\"\n"
+    ));
+    assert!(pydantic.contains("class class_(BaseModel):\n"));
+    assert!(pydantic.contains("    from_: str = Field(alias=\"from\")\n"));
+    assert!(pydantic.contains("    import_: Optional[str] = Field(\"x\", alias=\"import\")\n"));
+    assert!(pydantic.contains("    n_2nd: Optional[int] = Field(None, alias=\"2nd\")\n"));
+    assert!(pydantic.contains("    class_id: int\n"));
+    let plain = emit(Mode::Pydantic, &parse("User {\n    id id\n}\n"));
+    assert!(plain.contains("from pydantic import BaseModel\n"));
+}
+
+#[test]
+fn fixtures_fill_custom_type_columns() {
+    let doc = parse(
+        r#"
+        Product {
+            id id
+            money price
+            money discount?
+            money tax @default "0"
+            money[] history
+            money shipping example "5.00"
+        }
+        "#,
+    );
+    let opts = EmitOptions {
+        fixtures: Some(2),
+        ..EmitOptions::default()
+    };
+    let sql = emit_to_string(Mode::Sql, &doc, &opts).unwrap();
+    assert!(sql.contains("    (1, 'price 1', NULL, DEFAULT, ARRAY['history 1'], '5.00'),\n"));
+    assert!(sql.contains("    (2, 'price 2', NULL, DEFAULT, ARRAY['history 2'], '5.00');\n"));
+}
+
+#[test]
+fn foreign_key_arrays_have_plural_names() {
+    let doc = parse(
+        "
+        Category {
+            id id
+        }
+        Post {
+            id id
+        }
+        Post n--n Category
+        Post n--n Category: by_box
+        ",
+    );
+    let opts = EmitOptions {
+        naming: Naming {
+            fk_suffix: String::new(),
+            ..Naming::default()
+        },
+        ..EmitOptions::default()
+    };
+    let ts = emit_to_string(Mode::Ts, &doc, &opts).unwrap();
+    assert!(ts.contains("  categories: number[];\n"));
+    assert!(ts.contains("  by_boxes: number[];\n"));
+    let mongoose = emit_to_string(Mode::Mongoose, &doc, &opts).unwrap();
+    assert!(
+        mongoose.contains(
+            "  categories: [{ type: mongoose.Schema.Types.ObjectId, ref: 'Category' }],\n"
+        )
+    );
+    assert!(
+        mongoose
+            .contains("  by_boxes: [{ type: mongoose.Schema.Types.ObjectId, ref: 'Category' }],\n")
+    );
+}
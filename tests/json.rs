@@ -0,0 +1,66 @@
+use dialang::{
+    Naming,
+    emitters::{EmitOptions, JSON_VERSION, Mode, emit_to_string},
+    parse_str,
+};
+
+/// The contents of the first fenced code block after `after` in the README.
+fn readme_block(after: &str) -> &'static str {
+    let readme = include_str!("../README.md");
+    let start = readme.find(after).expect("README section is missing");
+    let fence = start + readme[start..].find("```").unwrap();
+    let body = fence + readme[fence..].find('\n').unwrap() + 1;
+    let end = body + readme[body..].find("```").unwrap();
+    &readme[body..end]
+}
+
+/// `json` without the whitespace between tokens, so differently laid out
+/// documents compare equal.
+fn compact(json: &str) -> String {
+    let mut out = String::new();
+    let (mut in_string, mut escaped) = (false, false);
+    for c in json.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if !c.is_whitespace() {
+            in_string = c == '"';
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[test]
+fn matches_documented_schema() {
+    let source = readme_block("Version 1, for this document");
+    let documented = readme_block("```json");
+    let doc = parse_str(source)
+        .unwrap()
+        .collapse_junctions(false, &Naming::default());
+    let json = emit_to_string(Mode::Json, &doc, &EmitOptions::default()).unwrap();
+    assert_eq!(compact(&json), compact(documented));
+}
+
+#[test]
+fn version_defaults_to_latest() {
+    let doc = parse_str("User {\n    id id\n}\n").unwrap();
+    let json = emit_to_string(Mode::Json, &doc, &EmitOptions::default()).unwrap();
+    assert!(json.starts_with(&format!("{{\n  \"version\": {JSON_VERSION},\n")));
+}
+
+#[test]
+fn requested_version_is_written() {
+    let doc = parse_str("User {\n    id id\n}\n").unwrap();
+    let opts = EmitOptions {
+        json_version: Some(1),
+        ..EmitOptions::default()
+    };
+    let json = emit_to_string(Mode::Json, &doc, &opts).unwrap();
+    assert!(json.contains("\"version\": 1,"));
+}
@@ -0,0 +1,167 @@
+mod common;
+
+use common::parse;
+use dialang::{LineStyle, parse_str};
+
+#[test]
+fn quoted_names_and_labels() {
+    let doc = parse(
+        r#"
+        "Order Item" {
+            id id
+        }
+        Order {
+            id id
+        }
+        "Order Item" n--1 Order: "belongs to"
+        "Order Item" n--1 Order: "the \"main\" one"
+        "#,
+    );
+    assert_eq!(doc.defs[0].name, "Order Item");
+    let [belongs, main] = doc.links.as_slice() else {
+        panic!("expected two links, got {:?}", doc.links);
+    };
+    assert_eq!(belongs.from, "Order Item");
+    assert_eq!(belongs.label.as_deref(), Some("belongs to"));
+    assert_eq!(main.label.as_deref(), Some(r#"the "main" one"#));
+}
+
+#[test]
+fn nullable_unique_and_default_fields() {
+    let doc = parse(
+        r#"
+        User {
+            id id
+            string email @unique
+            int age?
+            string role @default "'member'"
+        }
+        "#,
+    );
+    let [id, email, age, role] = doc.defs[0].fields.as_slice() else {
+        panic!("expected four fields, got {:?}", doc.defs[0].fields);
+    };
+    assert!(!id.nullable && !id.unique && id.default.is_none());
+    assert!(email.unique && !email.nullable);
+    assert!(age.nullable && !age.unique);
+    assert_eq!(age.name, "age");
+    assert_eq!(role.default.as_deref(), Some("'member'"));
+}
+
+#[test]
+fn comments_are_ignored_everywhere() {
+    let commented = parse(
+        r#"
+        # Billing
+        dialang 1.0 # the grammar
+
+        # the client
+        Client {
+            id id
+        }
+        Invoice @kind billing { # issued monthly
+            # the key
+            id id
+            float total # after taxes
+
+            check (total > 0) # no refunds
+        }
+        Invoice n--1 Client: "payer" # who pays
+        Invoice n--1 Client {
+            # when
+            date sent
+        }
+        # the end
+        "#,
+    );
+    let plain = parse(
+        r#"
+        dialang 1.0
+        Client {
+            id id
+        }
+        Invoice @kind billing {
+            id id
+            float total
+            check (total > 0)
+        }
+        Invoice n--1 Client: "payer"
+        Invoice n--1 Client {
+            date sent
+        }
+        "#,
+    );
+    assert_eq!(format!("{commented:?}"), format!("{plain:?}"));
+}
+
+#[test]
+fn links_can_carry_fields() {
+    let doc = parse(
+        "
+        Student {
+            id id
+        }
+        Course {
+            id id
+        }
+        Student n--n Course: enrollment {
+            date enrolled_on
+            int grade?
+        }
+        Student n--1 Course: favorite
+        ",
+    );
+    let [enrollment, favorite] = doc.links.as_slice() else {
+        panic!("expected two links, got {:?}", doc.links);
+    };
+    let fields: Vec<(&str, &str)> = enrollment
+        .fields
+        .iter()
+        .map(|field| (field.field_type.as_str(), field.name.as_str()))
+        .collect();
+    assert_eq!(fields, [("date", "enrolled_on"), ("int", "grade")]);
+    assert!(enrollment.fields[1].nullable);
+    assert!(favorite.fields.is_empty());
+}
+
+#[test]
+fn enum_field_types_keep_their_values() {
+    let doc = parse(
+        r#"
+        User {
+            id id
+            enum(active, suspended, "on hold") status
+            string name
+        }
+        "#,
+    );
+    let [id, status, name] = doc.defs[0].fields.as_slice() else {
+        panic!("expected three fields, got {:?}", doc.defs[0].fields);
+    };
+    assert_eq!(status.field_type, "enum");
+    assert_eq!(status.variants, ["active", "suspended", "on hold"]);
+    assert!(status.is_enum());
+    for scalar in [id, name] {
+        assert!(!scalar.is_enum() && scalar.variants.is_empty());
+    }
+    assert_eq!(name.field_type, "string");
+}
+
+#[test]
+fn link_styles_that_would_break_dot_are_rejected() {
+    let link = |annotations: &str| {
+        let source = format!("User {{\n    id id\n}}\nUser n--1 User {annotations}\n");
+        parse_str(&source).map_err(|err| err.to_string())
+    };
+    let ok = link(r##"@style dotted @color "#a0b1c2""##).unwrap();
+    assert_eq!(ok.links[0].style, Some(LineStyle::Dotted));
+    assert_eq!(ok.links[0].color.as_deref(), Some("#a0b1c2"));
+    assert!(link("@color navy").is_ok());
+    let err = link(r#"@color "red];x""#).unwrap_err();
+    assert!(
+        err.contains("@color must be a color name or `#rrggbb`"),
+        "{err}"
+    );
+    let err = link("@style wavy").unwrap_err();
+    assert!(err.contains("@style must be"), "{err}");
+}